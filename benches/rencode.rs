@@ -0,0 +1,105 @@
+#[macro_use]
+extern crate criterion;
+extern crate deluge;
+
+use criterion::{Criterion, Throughput};
+#[cfg(feature = "serde")]
+use deluge::rencode::{decode, encode};
+use deluge::rencode::Value;
+use std::collections::BTreeMap;
+
+fn s(v: &str) -> Value {
+    Value::String(v.into())
+}
+
+// roughly what get_torrents_status returns for the default columns of the web ui
+fn status(i: usize) -> Value {
+    let seeding = i.is_multiple_of(3);
+    let mut map = BTreeMap::new();
+    map.insert("name".into(), s(&format!("ubuntu-15.{:02}-desktop-amd64.iso", i % 100)));
+    map.insert("state".into(), s(if seeding { "Seeding" } else { "Downloading" }));
+    map.insert("save_path".into(), s("/home/deluge/downloads"));
+    map.insert("tracker_host".into(), s("ubuntu.com"));
+    map.insert("label".into(), s(""));
+    map.insert("paused".into(), Value::Bool(false));
+    map.insert("is_finished".into(), Value::Bool(seeding));
+    map.insert("total_size".into(), Value::I64(1178599424 + i as i64));
+    map.insert("total_done".into(), Value::I64(589299712));
+    map.insert("total_uploaded".into(), Value::I64(3 * 1178599424));
+    map.insert("num_peers".into(), Value::I64((i % 50) as i64));
+    map.insert("num_seeds".into(), Value::I64((i % 20) as i64));
+    map.insert("download_payload_rate".into(), Value::I64(250_000));
+    map.insert("upload_payload_rate".into(), Value::I64(50_000));
+    map.insert("eta".into(), Value::I64(3600));
+    map.insert("queue".into(), Value::I64(i as i64));
+    map.insert("progress".into(), Value::F64(50.0));
+    map.insert("ratio".into(), Value::F64(2.75));
+    map.insert("distributed_copies".into(), Value::F64(12.5));
+    map.insert("time_added".into(), Value::F64(1446076800.0 + i as f64));
+    Value::Dict(map)
+}
+
+fn session(torrents: usize) -> Value {
+    Value::Dict((0..torrents).map(|i| (format!("{:040x}", i), status(i))).collect())
+}
+
+fn files(count: usize) -> Value {
+    Value::List((0..count).map(|i| {
+        let mut map = BTreeMap::new();
+        map.insert("index".into(), Value::I64(i as i64));
+        map.insert("path".into(), s(&format!("collection/disc {}/track {:04}.flac", i / 100, i)));
+        map.insert("size".into(), Value::I64(31_457_280));
+        map.insert("offset".into(), Value::I64(i as i64 * 31_457_280));
+        Value::Dict(map)
+    }).collect())
+}
+
+fn inputs() -> Vec<(&'static str, Value)> {
+    vec![("session_1k", session(1_000)), ("session_10k", session(10_000)), ("file_list", files(5_000))]
+}
+
+#[cfg(feature = "serde")]
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+    for (name, value) in inputs() {
+        let data = encode(&value).unwrap();
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| decode::<Value, _>(&data[..]).unwrap()));
+    }
+    group.finish();
+}
+
+#[cfg(feature = "serde")]
+fn bench_encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode");
+    for (name, value) in inputs() {
+        group.throughput(Throughput::Bytes(encode(&value).unwrap().len() as u64));
+        group.bench_function(name, |b| b.iter(|| encode(&value).unwrap()));
+    }
+    group.finish();
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for (name, value) in inputs() {
+        let data = value.to_bytes();
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_function(name, |b| b.iter(|| Value::parse(&data).unwrap()));
+    }
+    group.finish();
+}
+
+fn bench_to_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("to_bytes");
+    for (name, value) in inputs() {
+        group.throughput(Throughput::Bytes(value.to_bytes().len() as u64));
+        group.bench_function(name, |b| b.iter(|| value.to_bytes()));
+    }
+    group.finish();
+}
+
+#[cfg(feature = "serde")]
+criterion_group!(benches, bench_decode, bench_encode, bench_parse, bench_to_bytes);
+#[cfg(not(feature = "serde"))]
+criterion_group!(benches, bench_parse, bench_to_bytes);
+criterion_main!(benches);
@@ -1,19 +1,51 @@
-#![feature(custom_derive, plugin)]
-#![plugin(serde_macros)]
-
 extern crate deluge;
 extern crate serde;
 
 use deluge::rencode::{decode, encode};
 use deluge::rencode::Value;
+use serde::ser::{self, Serialize, Serializer};
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Debug)]
 struct Msg {
     name: Value,
     code: Value,
 }
 
+// what #[derive(Serialize)] would generate, written by hand so the example builds on stable
+impl Serialize for Msg {
+
+    fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), S::Error> {
+        serializer.visit_map(MsgVisitor {
+            msg: self,
+            field: 0,
+        })
+    }
+
+}
+
+struct MsgVisitor<'a> {
+    msg: &'a Msg,
+    field: usize,
+}
+
+impl<'a> ser::MapVisitor for MsgVisitor<'a> {
+
+    fn visit<S: Serializer>(&mut self, serializer: &mut S) -> Result<Option<()>, S::Error> {
+        self.field += 1;
+        match self.field {
+            1 => Ok(Some(try!(serializer.visit_map_elt("name", &self.msg.name)))),
+            2 => Ok(Some(try!(serializer.visit_map_elt("code", &self.msg.code)))),
+            _ => Ok(None),
+        }
+    }
+
+    fn len(&self) -> Option<usize> {
+        Some(2)
+    }
+
+}
+
 fn main() {
     let orig = Msg {
         name: Value::String("abc".into()),
@@ -0,0 +1,10 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate deluge;
+
+use deluge::rencode::{decode, Value};
+
+fuzz_target!(|data: &[u8]| {
+    // errors are fine, panics are not
+    let _ = decode::<Value, _>(data);
+});
@@ -0,0 +1,14 @@
+#![no_main]
+#[macro_use] extern crate libfuzzer_sys;
+extern crate deluge;
+
+use deluge::rencode::Value;
+
+fuzz_target!(|data: &[u8]| {
+    // errors are fine, panics are not
+    if let Ok(value) = Value::parse(data) {
+        // whatever parses must survive a round trip through to_bytes
+        let bytes = value.to_bytes();
+        assert_eq!(Value::parse(&bytes).unwrap().to_bytes(), bytes);
+    }
+});
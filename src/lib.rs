@@ -1,4 +1,9 @@
+#[cfg(feature = "rencode")]
 extern crate byteorder;
+#[cfg(feature = "serde")]
 extern crate serde;
 
+pub mod pieces;
+#[cfg(feature = "rencode")]
 pub mod rencode;
+pub mod units;
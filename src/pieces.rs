@@ -0,0 +1,231 @@
+//! Piece-level progress, as reported by the `pieces` status key.
+
+use std::iter::FromIterator;
+
+#[cfg(feature = "rencode")]
+use rencode::Value;
+
+/// The state of a single piece, numbered like the daemon does.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PieceState {
+    /// Not downloaded, and no known peer has it.
+    Missing = 0,
+    /// Not downloaded, but available from a peer.
+    Available = 1,
+    /// Being downloaded from a peer.
+    Downloading = 2,
+    /// Downloaded and verified.
+    Completed = 3,
+}
+
+impl PieceState {
+
+    /// Returns the state numbered `n`, or `None` if there is no such state.
+    pub fn from_i64(n: i64) -> Option<PieceState> {
+        match n {
+            0 => Some(PieceState::Missing),
+            1 => Some(PieceState::Available),
+            2 => Some(PieceState::Downloading),
+            3 => Some(PieceState::Completed),
+            _ => None,
+        }
+    }
+
+    fn from_bits(bits: u8) -> PieceState {
+        match bits & 3 {
+            0 => PieceState::Missing,
+            1 => PieceState::Available,
+            2 => PieceState::Downloading,
+            _ => PieceState::Completed,
+        }
+    }
+
+}
+
+/// Number of pieces in each state.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PieceHistogram {
+    pub missing: usize,
+    pub available: usize,
+    pub downloading: usize,
+    pub completed: usize,
+}
+
+/// The state of every piece of a torrent, packed four pieces to a byte.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PieceMap {
+    bits: Vec<u8>,
+    len: usize,
+}
+
+impl PieceMap {
+
+    pub fn new() -> PieceMap {
+        PieceMap::default()
+    }
+
+    /// Decodes the `pieces` status key, a list with the number of each piece's state.
+    ///
+    /// The daemon sends NONE instead of a list for torrents that are seeding or don't have their
+    /// metadata yet. That, or anything else that isn't a list of states, returns `None`.
+    #[cfg(feature = "rencode")]
+    pub fn from_value(value: &Value) -> Option<PieceMap> {
+        value.as_list().and_then(|states| {
+            states.iter()
+                .map(|state| match *state {
+                    Value::I64(n) => PieceState::from_i64(n),
+                    _ => None,
+                })
+                .collect()
+        })
+    }
+
+    pub fn push(&mut self, state: PieceState) {
+        let shift = self.len % 4 * 2;
+        if shift == 0 {
+            self.bits.push(0);
+        }
+        // the new piece is always in the last byte
+        if let Some(byte) = self.bits.last_mut() {
+            *byte |= (state as u8) << shift;
+        }
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the state of piece `index`, or `None` if it's out of range.
+    pub fn get(&self, index: usize) -> Option<PieceState> {
+        if index < self.len {
+            Some(PieceState::from_bits(self.bits[index / 4] >> (index % 4 * 2)))
+        } else {
+            None
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            map: self,
+            index: 0,
+        }
+    }
+
+    pub fn completed_count(&self) -> usize {
+        self.iter().filter(|&state| state == PieceState::Completed).count()
+    }
+
+    /// Counts the pieces in each state.
+    pub fn availability_histogram(&self) -> PieceHistogram {
+        let mut histogram = PieceHistogram::default();
+        for state in self.iter() {
+            match state {
+                PieceState::Missing => histogram.missing += 1,
+                PieceState::Available => histogram.available += 1,
+                PieceState::Downloading => histogram.downloading += 1,
+                PieceState::Completed => histogram.completed += 1,
+            }
+        }
+        histogram
+    }
+
+}
+
+impl FromIterator<PieceState> for PieceMap {
+
+    fn from_iter<I: IntoIterator<Item = PieceState>>(iter: I) -> PieceMap {
+        let mut map = PieceMap::new();
+        for state in iter {
+            map.push(state);
+        }
+        map
+    }
+
+}
+
+impl<'a> IntoIterator for &'a PieceMap {
+
+    type Item = PieceState;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+
+}
+
+/// Iterator over the state of each piece, in order.
+pub struct Iter<'a> {
+    map: &'a PieceMap,
+    index: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+
+    type Item = PieceState;
+
+    fn next(&mut self) -> Option<PieceState> {
+        let state = self.map.get(self.index);
+        if state.is_some() {
+            self.index += 1;
+        }
+        state
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.map.len - self.index;
+        (remaining, Some(remaining))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PieceHistogram, PieceMap, PieceState};
+    use super::PieceState::*;
+
+    #[test]
+    fn test_piece_map() {
+        let states = [Completed, Missing, Available, Downloading, Completed, Available];
+        let map: PieceMap = states.iter().cloned().collect();
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.iter().collect::<Vec<_>>(), states);
+        assert_eq!(map.get(4), Some(Completed));
+        assert_eq!(map.get(6), None);
+        assert_eq!(map.completed_count(), 2);
+        assert_eq!(map.availability_histogram(), PieceHistogram {
+            missing: 1,
+            available: 2,
+            downloading: 1,
+            completed: 2,
+        });
+        assert!(PieceMap::new().is_empty());
+        assert_eq!(PieceMap::new().get(0), None);
+    }
+
+    #[test]
+    fn test_piece_state_from_i64() {
+        assert_eq!(PieceState::from_i64(3), Some(Completed));
+        assert_eq!(PieceState::from_i64(4), None);
+        assert_eq!(PieceState::from_i64(-1), None);
+    }
+
+    #[cfg(feature = "rencode")]
+    #[test]
+    fn test_piece_map_from_value() {
+        use rencode::Value;
+
+        let value = Value::List(vec![Value::I64(3), Value::I64(0), Value::I64(2)]);
+        let map = PieceMap::from_value(&value).unwrap();
+        assert_eq!(map.iter().collect::<Vec<_>>(), [Completed, Missing, Downloading]);
+        assert_eq!(PieceMap::from_value(&Value::List(vec![])), Some(PieceMap::new()));
+        assert_eq!(PieceMap::from_value(&Value::None), None);
+        assert_eq!(PieceMap::from_value(&Value::List(vec![Value::I64(5)])), None);
+        assert_eq!(PieceMap::from_value(&Value::List(vec![Value::Bool(true)])), None);
+    }
+}
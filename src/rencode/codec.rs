@@ -0,0 +1,412 @@
+// Encoding and decoding of `Value` without going through serde.
+
+use byteorder::{BigEndian, ByteOrder};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::num::ParseIntError;
+use std::str::{self, Utf8Error};
+use std::{i8, i16, i32, i64};
+
+use super::consts::*;
+use super::value::Value;
+
+#[derive(Debug)]
+pub enum Error {
+    DepthLimitExceeded,
+    ParseIntError(ParseIntError),
+    Syntax(&'static str),
+    TrailingBytes,
+    UnexpectedEOF,
+    Utf8Error(Utf8Error),
+}
+
+impl From<ParseIntError> for Error {
+    fn from(err: ParseIntError) -> Error {
+        Error::ParseIntError(err)
+    }
+}
+
+impl From<Utf8Error> for Error {
+    fn from(err: Utf8Error) -> Error {
+        Error::Utf8Error(err)
+    }
+}
+
+// Reads a big-endian number of up to 8 bytes. byteorder's readers cast the slice to a possibly
+// misaligned pointer, which debug builds abort on.
+pub fn read_be(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| acc << 8 | b as u64)
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+
+    fn next(&mut self) -> Result<u8, Error> {
+        match self.data.split_first() {
+            Some((&byte, rest)) => {
+                self.data = rest;
+                Ok(byte)
+            }
+            None => Err(Error::UnexpectedEOF),
+        }
+    }
+
+    fn peek(&self) -> Result<u8, Error> {
+        self.data.first().cloned().ok_or(Error::UnexpectedEOF)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if n > self.data.len() {
+            return Err(Error::UnexpectedEOF);
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    // Takes the bytes up to `delim`, consuming the delimiter.
+    fn take_until(&mut self, delim: u8) -> Result<&'a [u8], Error> {
+        match self.data.iter().position(|&b| b == delim) {
+            Some(pos) => {
+                let head = try!(self.take(pos));
+                self.data = &self.data[1..];
+                Ok(head)
+            }
+            None => Err(Error::UnexpectedEOF),
+        }
+    }
+
+    fn parse_string(&mut self, len: usize) -> Result<Value, Error> {
+        let bytes = try!(self.take(len));
+        Ok(Value::String(try!(str::from_utf8(bytes)).into()))
+    }
+
+    fn parse_embed_string(&mut self, byte: u8) -> Result<Value, Error> {
+        let len = try!(embedded(byte, STR_FIXED_START, STR_FIXED_COUNT).ok_or(Error::Syntax("invalid string typecode")));
+        self.parse_string(len as usize)
+    }
+
+    fn parse_prefixed_string(&mut self) -> Result<Value, Error> {
+        let digits = try!(str::from_utf8(try!(self.take_until(b':'))));
+        let len: usize = try!(digits.parse());
+        self.parse_string(len)
+    }
+
+    fn parse_int_digits(&mut self) -> Result<Value, Error> {
+        let digits = try!(str::from_utf8(try!(self.take_until(TERM))));
+        if digits.starts_with('-') {
+            Ok(Value::I64(try!(digits.parse())))
+        } else {
+            Ok(Value::U64(try!(digits.parse())))
+        }
+    }
+
+    fn parse_embed_pos(&mut self, byte: u8) -> Result<Value, Error> {
+        let n = try!(embedded(byte, INT_POS_FIXED_START, INT_POS_FIXED_COUNT).ok_or(Error::Syntax("invalid int typecode")));
+        Ok(Value::I64(n as i64))
+    }
+
+    fn parse_embed_neg(&mut self, byte: u8) -> Result<Value, Error> {
+        let n = try!(embedded(byte, INT_NEG_FIXED_START, INT_NEG_FIXED_COUNT).ok_or(Error::Syntax("invalid int typecode")));
+        Ok(Value::I64(-1 - n as i64))
+    }
+
+    fn parse_key(&mut self) -> Result<String, Error> {
+        match try!(self.parse()) {
+            Value::String(key) => Ok(key),
+            _ => Err(Error::Syntax("dict keys must be strings")),
+        }
+    }
+
+    fn parse_nested(&mut self, byte: u8) -> Result<Value, Error> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Error::DepthLimitExceeded);
+        }
+        self.depth += 1;
+        let value = match byte {
+            LIST => {
+                let mut list = Vec::new();
+                while try!(self.peek()) != TERM {
+                    list.push(try!(self.parse()));
+                }
+                self.data = &self.data[1..];
+                Value::List(list)
+            }
+            DICT => {
+                let mut dict = BTreeMap::new();
+                while try!(self.peek()) != TERM {
+                    let key = try!(self.parse_key());
+                    dict.insert(key, try!(self.parse()));
+                }
+                self.data = &self.data[1..];
+                Value::Dict(dict)
+            }
+            LIST_FIXED_START...LIST_FIXED_END => {
+                let len = try!(embedded(byte, LIST_FIXED_START, LIST_FIXED_COUNT)
+                    .ok_or(Error::Syntax("invalid list typecode")));
+                let mut list = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    list.push(try!(self.parse()));
+                }
+                Value::List(list)
+            }
+            _ => {
+                let len = try!(embedded(byte, DICT_FIXED_START, DICT_FIXED_COUNT)
+                    .ok_or(Error::Syntax("invalid dict typecode")));
+                let mut dict = BTreeMap::new();
+                for _ in 0..len {
+                    let key = try!(self.parse_key());
+                    dict.insert(key, try!(self.parse()));
+                }
+                Value::Dict(dict)
+            }
+        };
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    fn parse(&mut self) -> Result<Value, Error> {
+        let start = self.data;
+        let byte = try!(self.next());
+        match byte {
+            // the first digit is part of the length prefix, parse it from the input again
+            b'0'...b'9' => {
+                self.data = start;
+                self.parse_prefixed_string()
+            }
+            STR_FIXED_START...STR_FIXED_END => self.parse_embed_string(byte),
+            INT => self.parse_int_digits(),
+            I8 => Ok(Value::I64(read_be(try!(self.take(1))) as i8 as i64)),
+            I16 => Ok(Value::I64(read_be(try!(self.take(2))) as i16 as i64)),
+            I32 => Ok(Value::I64(read_be(try!(self.take(4))) as i32 as i64)),
+            I64 => Ok(Value::I64(read_be(try!(self.take(8))) as i64)),
+            F32 => Ok(Value::F64(f32::from_bits(read_be(try!(self.take(4))) as u32) as f64)),
+            F64 => Ok(Value::F64(f64::from_bits(read_be(try!(self.take(8)))))),
+            INT_POS_FIXED_START...INT_POS_FIXED_END => self.parse_embed_pos(byte),
+            INT_NEG_FIXED_START...INT_NEG_FIXED_END => self.parse_embed_neg(byte),
+            TRUE => Ok(Value::Bool(true)),
+            FALSE => Ok(Value::Bool(false)),
+            NONE => Ok(Value::None),
+            LIST | DICT | LIST_FIXED_START...LIST_FIXED_END | DICT_FIXED_START...DICT_FIXED_END => {
+                self.parse_nested(byte)
+            }
+            _ => Err(Error::Syntax("unexpected byte")),
+        }
+    }
+
+}
+
+/// Parses exactly one value from `data`.
+pub fn parse(data: &[u8]) -> Result<Value, Error> {
+    let mut parser = Parser {
+        data: data,
+        depth: 0,
+    };
+    let value = try!(parser.parse());
+    if !parser.data.is_empty() {
+        return Err(Error::TrailingBytes);
+    }
+    Ok(value)
+}
+
+// i64 bounds of the integers embedded in typecodes and of the fixed-width integers, for use in
+// patterns
+const INT_NEG_FIXED_MIN: i64 = -(INT_NEG_FIXED_COUNT as i64);
+const INT_POS_FIXED_MAX: i64 = INT_POS_FIXED_END as i64;
+const I8_MIN: i64 = i8::MIN as i64;
+const I8_MAX: i64 = i8::MAX as i64;
+const I16_MIN: i64 = i16::MIN as i64;
+const I16_MAX: i64 = i16::MAX as i64;
+const I32_MIN: i64 = i32::MIN as i64;
+const I32_MAX: i64 = i32::MAX as i64;
+
+// The writers below are shared with the serde encoder so that both produce the same bytes.
+
+pub fn write_i64<W: Write>(w: &mut W, v: i64) -> io::Result<()> {
+    let (typecode, len) = match v {
+        // the typecode is computed as i64, v is known to be in range so it fits in a u8
+        INT_NEG_FIXED_MIN...-1 => return w.write_all(&[(INT_NEG_FIXED_START as i64 - 1 - v) as u8]),
+        0...INT_POS_FIXED_MAX => return w.write_all(&[(INT_POS_FIXED_START as i64 + v) as u8]),
+        I8_MIN...I8_MAX => (I8, 1),
+        I16_MIN...I16_MAX => (I16, 2),
+        I32_MIN...I32_MAX => (I32, 4),
+        _ => (I64, 8),
+    };
+    let mut buf = [0; 9];
+    buf[0] = typecode;
+    match len {
+        1 => buf[1] = v as i8 as u8,
+        2 => BigEndian::write_i16(&mut buf[1..], v as i16),
+        4 => BigEndian::write_i32(&mut buf[1..], v as i32),
+        _ => BigEndian::write_i64(&mut buf[1..], v),
+    }
+    w.write_all(&buf[..1 + len])
+}
+
+pub fn write_u64<W: Write>(w: &mut W, v: u64) -> io::Result<()> {
+    if v <= i64::MAX as u64 {
+        write_i64(w, v as i64)
+    } else {
+        // too big for I64, the reference implementation sends it as a decimal string
+        try!(w.write_all(&[INT]));
+        try!(write!(w, "{}", v));
+        w.write_all(&[TERM])
+    }
+}
+
+pub fn write_f64<W: Write>(w: &mut W, v: f64) -> io::Result<()> {
+    let mut buf = [F64, 0, 0, 0, 0, 0, 0, 0, 0];
+    BigEndian::write_f64(&mut buf[1..], v);
+    w.write_all(&buf)
+}
+
+// Writes a string, embedding its length in the typecode when it is short enough and `fixed` is set.
+pub fn write_str<W: Write>(w: &mut W, v: &str, fixed: bool) -> io::Result<()> {
+    if fixed && v.len() < STR_FIXED_COUNT as usize {
+        try!(w.write_all(&[STR_FIXED_START + v.len() as u8]));
+    } else {
+        try!(write!(w, "{}:", v.len()));
+    }
+    w.write_all(v.as_bytes())
+}
+
+// Writes the typecode opening a list of `len` elements, `None` if the length is unknown or fixed
+// types are not wanted. Returns whether the list must be closed with TERM.
+pub fn write_list_start<W: Write>(w: &mut W, len: Option<usize>) -> io::Result<bool> {
+    match len {
+        Some(len) if len < LIST_FIXED_COUNT as usize => {
+            try!(w.write_all(&[LIST_FIXED_START + len as u8]));
+            Ok(false)
+        }
+        _ => {
+            try!(w.write_all(&[LIST]));
+            Ok(true)
+        }
+    }
+}
+
+// Same as `write_list_start`, for dicts.
+pub fn write_dict_start<W: Write>(w: &mut W, len: Option<usize>) -> io::Result<bool> {
+    match len {
+        Some(len) if len < DICT_FIXED_COUNT as usize => {
+            try!(w.write_all(&[DICT_FIXED_START + len as u8]));
+            Ok(false)
+        }
+        _ => {
+            try!(w.write_all(&[DICT]));
+            Ok(true)
+        }
+    }
+}
+
+/// Writes the encoding of `value`, producing the same bytes as the serde encoder.
+pub fn write<W: Write>(w: &mut W, value: &Value) -> io::Result<()> {
+    match *value {
+        Value::None => w.write_all(&[NONE]),
+        Value::Bool(v) => w.write_all(&[if v { TRUE } else { FALSE }]),
+        Value::I64(v) => write_i64(w, v),
+        Value::U64(v) => write_u64(w, v),
+        Value::F64(v) => write_f64(w, v),
+        Value::String(ref v) => write_str(w, v, true),
+        Value::List(ref v) => {
+            let term = try!(write_list_start(w, Some(v.len())));
+            for elem in v {
+                try!(write(w, elem));
+            }
+            if term {
+                try!(w.write_all(&[TERM]));
+            }
+            Ok(())
+        }
+        Value::Dict(ref v) => {
+            let term = try!(write_dict_start(w, Some(v.len())));
+            for (key, val) in v {
+                try!(write_str(w, key, true));
+                try!(write(w, val));
+            }
+            if term {
+                try!(w.write_all(&[TERM]));
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, read_be, Error};
+    use super::super::consts::*;
+    use super::super::value::Value;
+    use std::collections::BTreeMap;
+    use std::iter::repeat;
+    use std::{i64, u64};
+
+    fn roundtrip(value: Value) {
+        let data = value.to_bytes();
+        assert_eq!(parse(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn test_codec_roundtrip() {
+        for &n in &[0, 43, 44, -1, -32, -33, 127, -128, 128, -129, 32767, -32768, 32768, 2147483647,
+                    -2147483648, 2147483648, i64::MIN, i64::MAX] {
+            roundtrip(Value::I64(n));
+        }
+        roundtrip(Value::U64(u64::MAX));
+        roundtrip(Value::F64(2.75));
+        roundtrip(Value::None);
+        roundtrip(Value::Bool(true));
+        roundtrip(Value::String(repeat('a').take(63).collect()));
+        roundtrip(Value::String(repeat('a').take(64).collect()));
+        roundtrip(Value::List((0..63).map(Value::I64).collect()));
+        roundtrip(Value::List((0..64).map(Value::I64).collect()));
+        for &n in &[24, 25] {
+            let dict = (0..n).map(|i| (format!("{:02}", i), Value::I64(i))).collect::<BTreeMap<_, _>>();
+            roundtrip(Value::Dict(dict));
+        }
+    }
+
+    #[test]
+    fn test_codec_bytes() {
+        assert_eq!(Value::I64(44).to_bytes(), &[I8, 44]);
+        assert_eq!(Value::I64(-32).to_bytes(), &[101]);
+        assert_eq!(Value::String("abc".into()).to_bytes(), &[131, b'a', b'b', b'c']);
+        assert_eq!(parse(b"8:rustlang").unwrap(), Value::String("rustlang".into()));
+        assert_eq!(parse(&[F32, 0x3f, 0xc0, 0, 0]).unwrap(), Value::F64(1.5));
+        assert_eq!(parse(&[LIST, 1, TERM]).unwrap(), Value::List(vec![Value::I64(1)]));
+    }
+
+    #[test]
+    fn test_codec_read_be() {
+        assert_eq!(read_be(&[]), 0);
+        assert_eq!(read_be(&[0xff]) as i8, -1);
+        assert_eq!(read_be(&[0x80, 0]) as i16, -32768);
+        assert_eq!(read_be(&[1, 2, 3, 4, 5, 6, 7, 8]), 0x0102030405060708);
+    }
+
+    #[test]
+    fn test_codec_errors() {
+        match parse(&[1, 2]) {
+            Err(Error::TrailingBytes) => {}
+            other => panic!("{:?}", other),
+        }
+        match parse(&[103, 1, 2]) {
+            Err(Error::Syntax(_)) => {}
+            other => panic!("{:?}", other),
+        }
+        match parse(&repeat(LIST).take(10_000).collect::<Vec<u8>>()) {
+            Err(Error::DepthLimitExceeded) => {}
+            other => panic!("{:?}", other),
+        }
+        match parse(&[]) {
+            Err(Error::UnexpectedEOF) => {}
+            other => panic!("{:?}", other),
+        }
+        assert!(parse(b"5:abc").is_err());
+        assert!(parse(&[LIST, 1]).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 // type codes
 pub const LIST: u8 = 59;
 pub const DICT: u8 = 60;
-//const INT: u8 = 61;
+pub const INT: u8 = 61;
 pub const I8: u8 = 62;
 pub const I16: u8 = 63;
 pub const I32: u8 = 64;
@@ -12,3 +12,43 @@ pub const TRUE: u8 = 67;
 pub const FALSE: u8 = 68;
 pub const NONE: u8 = 69;
 pub const TERM: u8 = 127;
+
+// type code ranges 0..43, 70..101, 102..127, 128..191, 192..255
+//                   int    -int     dict      string    list
+
+// positive integers with value embedded in typecode.
+pub const INT_POS_FIXED_START: u8 = 0;
+pub const INT_POS_FIXED_COUNT: u8 = 44;
+pub const INT_POS_FIXED_END: u8 = INT_POS_FIXED_START + INT_POS_FIXED_COUNT - 1;
+
+// Negative integers with value embedded in typecode.
+pub const INT_NEG_FIXED_START: u8 = 70;
+pub const INT_NEG_FIXED_COUNT: u8 = 32;
+pub const INT_NEG_FIXED_END: u8 = INT_NEG_FIXED_START + INT_NEG_FIXED_COUNT - 1;
+
+// Strings with length embedded in typecode.
+pub const STR_FIXED_START: u8 = 128;
+pub const STR_FIXED_COUNT: u8 = 64;
+pub const STR_FIXED_END: u8 = STR_FIXED_START + STR_FIXED_COUNT - 1;
+
+// Lists with length embedded in typecode.
+pub const LIST_FIXED_START: u8 = STR_FIXED_START + STR_FIXED_COUNT;
+pub const LIST_FIXED_COUNT: u8 = 64;
+pub const LIST_FIXED_END: u8 = LIST_FIXED_START - 1 + LIST_FIXED_COUNT;
+
+// Dictionaries with length embedded in typecode.
+pub const DICT_FIXED_START: u8 = 102;
+pub const DICT_FIXED_COUNT: u8 = 25;
+pub const DICT_FIXED_END: u8 = DICT_FIXED_START + DICT_FIXED_COUNT - 1;
+
+// Maximum number of nested lists and dicts, deeper payloads are rejected before they can
+// exhaust the stack.
+pub const MAX_DEPTH: usize = 256;
+
+// Returns the value embedded in a typecode, or `None` if the typecode is outside of the range.
+pub fn embedded(byte: u8, start: u8, count: u8) -> Option<u8> {
+    match byte.checked_sub(start) {
+        Some(n) if n < count => Some(n),
+        _ => None,
+    }
+}
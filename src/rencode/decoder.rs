@@ -1,41 +1,20 @@
-use byteorder::{self, BigEndian, ReadBytesExt};
+use byteorder::{self, ReadBytesExt};
 use serde::de::{Deserialize, Deserializer, Error as DeserializerError, MapVisitor, SeqVisitor, Visitor};
+use std::cmp;
 use std::io::{self, Read};
 use std::num::ParseIntError;
+use std::ops::Range;
 use std::string::FromUtf8Error;
 
+use super::codec;
 use super::consts::*;
 
-// type code ranges 0..43, 70..101, 102..127, 128..191, 192..255
-//                   int    -int     dict      string    list
-
-// positive integers with value embedded in typecode.
-const INT_POS_FIXED_START: u8 = 0;
-const INT_POS_FIXED_COUNT: u8 = 44;
-const INT_POS_FIXED_END: u8 = INT_POS_FIXED_START + INT_POS_FIXED_COUNT - 1;
-
-// Negative integers with value embedded in typecode.
-const INT_NEG_FIXED_START: u8 = 70;
-const INT_NEG_FIXED_COUNT: u8 = 32;
-const INT_NEG_FIXED_END: u8 = INT_NEG_FIXED_START + INT_NEG_FIXED_COUNT - 1;
-
-// Strings with length embedded in typecode.
-const STR_FIXED_START: u8 = 128;
-const STR_FIXED_COUNT: u8 = 64;
-const STR_FIXED_END: u8 = STR_FIXED_START + STR_FIXED_COUNT - 1;
-
-// Lists with length embedded in typecode.
-const LIST_FIXED_START: u8 = STR_FIXED_START + STR_FIXED_COUNT;
-const LIST_FIXED_COUNT: u8 = 64;
-const LIST_FIXED_END: u8 = LIST_FIXED_START - 1 + LIST_FIXED_COUNT;
-
-// Dictionaries with length embedded in typecode.
-const DICT_FIXED_START: u8 = 102;
-const DICT_FIXED_COUNT: u8 = 25;
-const DICT_FIXED_END: u8 = DICT_FIXED_START + DICT_FIXED_COUNT - 1;
+// Largest buffer allocated up front when reading a string.
+const MAX_PREALLOC: usize = 64 * 1024;
 
 #[derive(Debug)]
 pub enum Error {
+    DepthLimitExceeded,
     EndOfStream,
     EndOfStruct,
     FromUtf8Error(FromUtf8Error),
@@ -96,13 +75,33 @@ impl DeserializerError for Error {
 
 }
 
+/// Options controlling how lenient the decoder is.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecodeOptions {
+    /// Skip dict entries whose value is NONE, as if the key was absent. Combined with
+    /// `#[serde(default)]`, this maps NONE to `Default::default()` for non-`Option` fields.
+    /// `Option` fields whose value is NONE, or whose entry is absent, decode to `None`.
+    pub skip_none_entries: bool,
+}
+
 struct Decoder<R: Read> {
     reader: R,
     peek: Option<u8>,
+    depth: usize,
+    options: DecodeOptions,
 }
 
 impl<R: Read> Decoder<R> {
 
+    fn new(reader: R, options: DecodeOptions) -> Decoder<R> {
+        Decoder {
+            reader: reader,
+            peek: None,
+            depth: 0,
+            options: options,
+        }
+    }
+
     fn next(&mut self) -> Result<u8, Error> {
         match self.peek.take() {
             Some(byte) => Ok(byte),
@@ -111,15 +110,34 @@ impl<R: Read> Decoder<R> {
     }
 
     fn peek(&mut self) -> Result<u8, Error> {
-        // make sure the peak is empty so that next doesn't use it
-        self.peek.take();
-
-        match self.next() {
-            Ok(byte) => {
+        match self.peek {
+            Some(byte) => Ok(byte),
+            None => {
+                let byte = try!(self.reader.read_u8());
                 self.peek = Some(byte);
                 Ok(byte)
-            },
-            Err(err) => Err(From::from(err)),
+            }
+        }
+    }
+
+    // When NONE entries are skipped, decodes a field absent from a dict as if its value was NONE,
+    // so that `Option` fields whose entry was skipped become `None`. Other types, and every field
+    // when the option is off, report the field as missing.
+    fn missing_value<V: Deserialize>(&self, field: &'static str) -> Result<V, Error> {
+        if !self.options.skip_none_entries {
+            return Err(Error::MissingField(field));
+        }
+        let mut decoder = Decoder::new(&[NONE][..], self.options);
+        Deserialize::deserialize(&mut decoder).map_err(|_| Error::MissingField(field))
+    }
+
+    // Consumes the next value if it's NONE and entries with NONE values are skipped.
+    fn skip_none(&mut self) -> Result<bool, Error> {
+        if self.options.skip_none_entries && try!(self.peek()) == NONE {
+            self.peek.take();
+            Ok(true)
+        } else {
+            Ok(false)
         }
     }
 
@@ -139,77 +157,131 @@ impl<R: Read> Decoder<R> {
         }
     }
 
-    fn take(&mut self, mut n: usize) -> Result<Vec<u8>, Error> {
-        let mut buff = Vec::new();
+    fn take(&mut self, n: usize) -> Result<Vec<u8>, Error> {
+        // the length comes from the input, don't trust it for the initial allocation
+        let mut buff = Vec::with_capacity(cmp::min(n, MAX_PREALLOC));
 
         if n == 0 {
             return Ok(buff);
         }
 
-        while n > 0 {
-            match self.next() {
-                Ok(byte) => buff.push(byte),
-                Err(err) => return Err(From::from(err)),
-            }
-            n -= 1;
+        if let Some(byte) = self.peek.take() {
+            buff.push(byte);
+        }
+        let rest = (n - buff.len()) as u64;
+        try!(Read::take(&mut self.reader, rest).read_to_end(&mut buff));
+        if buff.len() < n {
+            return Err(Error::UnexpectedEOF);
         }
 
         Ok(buff)
     }
 
-    fn parse_string(&mut self) -> Result<String, Error> {
-        let numstr = try!(String::from_utf8(try!(self.take_while(|b| b != b':'))));
-        let num: usize = try!(numstr.parse());
-        let newstr = try!(String::from_utf8(try!(self.take(num))));
+    fn parse_string(&mut self, first: u8) -> Result<String, Error> {
+        // the length prefix is accumulated digit by digit rather than collected into a String
+        let mut len = (first - b'0') as usize;
+        loop {
+            match try!(self.next()) {
+                b':' => break,
+                byte @ b'0'...b'9' => {
+                    len = try!(len.checked_mul(10)
+                        .and_then(|len| len.checked_add((byte - b'0') as usize))
+                        .ok_or_else(|| Error::syntax("string length overflow")));
+                }
+                _ => return Err(Error::syntax("invalid string length")),
+            }
+        }
+        let newstr = try!(String::from_utf8(try!(self.take(len))));
         Ok(newstr)
     }
 
     fn parse_embed_string(&mut self, byte: u8) -> Result<String, Error> {
-        let len = byte - STR_FIXED_START;
-        self.peek.take();
+        let len = try!(embedded(byte, STR_FIXED_START, STR_FIXED_COUNT).ok_or_else(|| Error::syntax("invalid string typecode")));
         let newstr = try!(String::from_utf8(try!(self.take(len as usize))));
         Ok(newstr)
     }
 
+    fn parse_int_digits(&mut self) -> Result<String, Error> {
+        let digits = try!(String::from_utf8(try!(self.take_while(|b| b != TERM))));
+        Ok(digits)
+    }
+
     fn parse_i8(&mut self) -> Result<i8, Error> {
         self.reader.read_i8().map_err(From::from)
     }
 
+    // Reads a big-endian number of `n` bytes, see `codec::read_be`.
+    fn parse_be(&mut self, n: usize) -> Result<u64, Error> {
+        let mut buf = [0; 8];
+        for byte in &mut buf[..n] {
+            *byte = try!(self.reader.read_u8());
+        }
+        Ok(codec::read_be(&buf[..n]))
+    }
+
     fn parse_i16(&mut self) -> Result<i16, Error> {
-        self.reader.read_i16::<BigEndian>().map_err(From::from)
+        Ok(try!(self.parse_be(2)) as i16)
     }
 
     fn parse_i32(&mut self) -> Result<i32, Error> {
-        self.reader.read_i32::<BigEndian>().map_err(From::from)
+        Ok(try!(self.parse_be(4)) as i32)
     }
 
     fn parse_i64(&mut self) -> Result<i64, Error> {
-        self.reader.read_i64::<BigEndian>().map_err(From::from)
+        Ok(try!(self.parse_be(8)) as i64)
     }
 
     fn parse_f32(&mut self) -> Result<f32, Error> {
-        self.reader.read_f32::<BigEndian>().map_err(From::from)
+        Ok(f32::from_bits(try!(self.parse_be(4)) as u32))
     }
 
     fn parse_f64(&mut self) -> Result<f64, Error> {
-        self.reader.read_f64::<BigEndian>().map_err(From::from)
+        Ok(f64::from_bits(try!(self.parse_be(8))))
     }
 
     fn parse_embed_pos(&mut self, byte: u8) -> Result<i8, Error> {
-        Ok((byte - INT_POS_FIXED_START) as i8)
+        let n = try!(embedded(byte, INT_POS_FIXED_START, INT_POS_FIXED_COUNT).ok_or_else(|| Error::syntax("invalid int typecode")));
+        Ok(n as i8)
     }
 
     fn parse_embed_neg(&mut self, byte: u8) -> Result<i8, Error> {
-        Ok(-((byte - INT_NEG_FIXED_START + 1) as i8))
+        let n = try!(embedded(byte, INT_NEG_FIXED_START, INT_NEG_FIXED_COUNT).ok_or_else(|| Error::syntax("invalid int typecode")));
+        // n is at most 31, -1 - n always fits in an i8
+        Ok(-1 - n as i8)
     }
 
-    fn build_fixed_visitor<'a>(&'a mut self, len: u8) -> FixedVisitor<'a, R> {
-        self.peek.take();
-        FixedVisitor {
+    fn build_fixed_visitor<'a>(&'a mut self, byte: u8, start: u8, count: u8) -> Result<FixedVisitor<'a, R>, Error> {
+        let len = try!(embedded(byte, start, count).ok_or_else(|| Error::syntax("invalid container typecode")));
+        Ok(FixedVisitor {
             decoder: self,
             count: 0,
             len: len,
+        })
+    }
+
+    fn visit_nested<V: Visitor>(&mut self, byte: u8, mut visitor: V) -> Result<V::Value, Error> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Error::DepthLimitExceeded);
         }
+        self.depth += 1;
+        let result = match byte {
+            LIST => visitor.visit_seq(&mut *self),
+            DICT => visitor.visit_map(&mut *self),
+            LIST_FIXED_START...LIST_FIXED_END => {
+                match self.build_fixed_visitor(byte, LIST_FIXED_START, LIST_FIXED_COUNT) {
+                    Ok(fixed) => visitor.visit_seq(fixed),
+                    Err(err) => Err(err),
+                }
+            }
+            _ => {
+                match self.build_fixed_visitor(byte, DICT_FIXED_START, DICT_FIXED_COUNT) {
+                    Ok(fixed) => visitor.visit_map(fixed),
+                    Err(err) => Err(err),
+                }
+            }
+        };
+        self.depth -= 1;
+        result
     }
 
 }
@@ -219,13 +291,26 @@ impl<R: Read> Deserializer for Decoder<R> {
     type Error = Error;
 
     fn visit<V: Visitor>(&mut self, mut visitor: V) -> Result<V::Value, Error> {
-        match self.peek() {
+        // TERM is left in place for the enclosing list or dict to consume
+        if try!(self.peek()) == TERM {
+            return Err(Error::EndOfStruct);
+        }
+        match self.next() {
             Ok(byte) => {
                 match byte {
-                    b'0'...b'9' => visitor.visit_string(try!(self.parse_string())),
+                    b'0'...b'9' => visitor.visit_string(try!(self.parse_string(byte))),
                     STR_FIXED_START...STR_FIXED_END => {
                         visitor.visit_string(try!(self.parse_embed_string(byte)))
                     }
+                    INT => {
+                        // integers that don't fit in an i64 are sent as decimal strings
+                        let digits = try!(self.parse_int_digits());
+                        if digits.starts_with('-') {
+                            visitor.visit_i64(try!(digits.parse()))
+                        } else {
+                            visitor.visit_u64(try!(digits.parse()))
+                        }
+                    }
                     I8 => visitor.visit_i8(try!(self.parse_i8())),
                     I16 => visitor.visit_i16(try!(self.parse_i16())),
                     I32 => visitor.visit_i32(try!(self.parse_i32())),
@@ -241,17 +326,9 @@ impl<R: Read> Deserializer for Decoder<R> {
                     TRUE => visitor.visit_bool(true),
                     FALSE => visitor.visit_bool(false),
                     NONE => visitor.visit_none(),
-                    LIST => visitor.visit_seq(self),
-                    LIST_FIXED_START...LIST_FIXED_END => {
-                        let len = byte - LIST_FIXED_START;
-                        visitor.visit_seq(self.build_fixed_visitor(len))
-                    }
-                    DICT => visitor.visit_map(self),
-                    DICT_FIXED_START...DICT_FIXED_END => {
-                        let len = byte - DICT_FIXED_START;
-                        visitor.visit_map(self.build_fixed_visitor(len))
+                    LIST | DICT | LIST_FIXED_START...LIST_FIXED_END | DICT_FIXED_START...DICT_FIXED_END => {
+                        self.visit_nested(byte, visitor)
                     }
-                    TERM => Err(Error::EndOfStruct),
                     _ => Err(Error::syntax("unexpected byte")),
                 }
             }
@@ -259,6 +336,15 @@ impl<R: Read> Deserializer for Decoder<R> {
         }
     }
 
+    fn visit_option<V: Visitor>(&mut self, mut visitor: V) -> Result<V::Value, Error> {
+        if try!(self.peek()) == NONE {
+            self.peek.take();
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
 }
 
 impl<R: Read> SeqVisitor for Decoder<R> {
@@ -291,12 +377,18 @@ impl<R: Read> MapVisitor for Decoder<R> {
     type Error = Error;
 
     fn visit_key<K: Deserialize>(&mut self) -> Result<Option<K>, Self::Error> {
-        match Deserialize::deserialize(self) {
-            Ok(val) => Ok(Some(val)),
-            Err(err) => {
-                match err {
-                    Error::EndOfStruct => Ok(None),
-                    _ => Err(err),
+        loop {
+            match Deserialize::deserialize(self) {
+                Ok(val) => {
+                    if !try!(self.skip_none()) {
+                        return Ok(Some(val));
+                    }
+                }
+                Err(err) => {
+                    match err {
+                        Error::EndOfStruct => return Ok(None),
+                        _ => return Err(err),
+                    }
                 }
             }
         }
@@ -313,6 +405,10 @@ impl<R: Read> MapVisitor for Decoder<R> {
         }
     }
 
+    fn missing_field<V: Deserialize>(&mut self, field: &'static str) -> Result<V, Self::Error> {
+        self.missing_value(field)
+    }
+
 }
 
 struct FixedVisitor<'a, R: Read + 'a> {
@@ -343,6 +439,11 @@ impl<'a, R: Read> SeqVisitor for FixedVisitor<'a, R> {
         Ok(())
     }
 
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.len - self.count) as usize;
+        (remaining, Some(remaining))
+    }
+
 }
 
 impl<'a, R: Read> MapVisitor for FixedVisitor<'a, R> {
@@ -350,17 +451,14 @@ impl<'a, R: Read> MapVisitor for FixedVisitor<'a, R> {
     type Error = Error;
 
     fn visit_key<K: Deserialize>(&mut self) -> Result<Option<K>, Self::Error> {
-        if self.count >= self.len {
-            Ok(None)
-        } else {
-            match Deserialize::deserialize(self.decoder) {
-                Ok(val) => {
-                    self.count += 1;
-                    Ok(Some(val))
-                },
-                Err(err) => Err(err),
+        while self.count < self.len {
+            let val = try!(Deserialize::deserialize(self.decoder));
+            self.count += 1;
+            if !try!(self.decoder.skip_none()) {
+                return Ok(Some(val));
             }
         }
+        Ok(None)
     }
 
     fn visit_value<V: Deserialize>(&mut self) -> Result<V, Self::Error> {
@@ -371,21 +469,177 @@ impl<'a, R: Read> MapVisitor for FixedVisitor<'a, R> {
         Ok(())
     }
 
+    fn missing_field<V: Deserialize>(&mut self, field: &'static str) -> Result<V, Self::Error> {
+        self.decoder.missing_value(field)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.len - self.count) as usize;
+        if self.decoder.options.skip_none_entries {
+            (0, Some(remaining))
+        } else {
+            (remaining, Some(remaining))
+        }
+    }
+
 }
 
 pub fn decode<T: Deserialize, R: Read>(reader: R) -> Result<T, Error> {
-    let mut decoder = Decoder {
-        reader: reader,
-        peek: None,
-    };
+    decode_with_options(reader, DecodeOptions::default())
+}
+
+pub fn decode_with_options<T: Deserialize, R: Read>(reader: R, options: DecodeOptions) -> Result<T, Error> {
+    let mut decoder = Decoder::new(reader, options);
     Deserialize::deserialize(&mut decoder)
 }
 
+/// Decodes the consecutive top-level values in `data`, returning each value along with the byte
+/// range it was decoded from so the raw bytes can be forwarded or cached without re-encoding.
+pub fn decode_spans<T: Deserialize>(data: &[u8]) -> Result<Vec<(T, Range<usize>)>, Error> {
+    // a byte stashed by peek has been read from the slice but not consumed yet
+    fn position(data: &[u8], decoder: &Decoder<&[u8]>) -> usize {
+        data.len() - decoder.reader.len() - decoder.peek.iter().count()
+    }
+
+    let mut decoder = Decoder::new(data, DecodeOptions::default());
+    let mut values = Vec::new();
+    loop {
+        let start = position(data, &decoder);
+        if start == data.len() {
+            return Ok(values);
+        }
+        let value = try!(Deserialize::deserialize(&mut decoder));
+        values.push((value, start..position(data, &decoder)));
+    }
+}
+
+fn visit_entries<K, V, M, F>(visitor: &mut M, f: &mut F) -> Result<(), Error>
+    where K: Deserialize, V: Deserialize, M: MapVisitor<Error = Error>, F: FnMut(K, V)
+{
+    while let Some(key) = try!(visitor.visit_key()) {
+        let value = try!(visitor.visit_value());
+        f(key, value);
+    }
+    visitor.end()
+}
+
+/// Decodes a dict one entry at a time, calling `f` with each key and value instead of building
+/// the whole map in memory.
+pub fn decode_map_streaming<K, V, R, F>(reader: R, mut f: F) -> Result<(), Error>
+    where K: Deserialize, V: Deserialize, R: Read, F: FnMut(K, V)
+{
+    let mut decoder = Decoder::new(reader, DecodeOptions::default());
+    match try!(decoder.next()) {
+        DICT => visit_entries(&mut decoder, &mut f),
+        byte @ DICT_FIXED_START...DICT_FIXED_END => {
+            let mut visitor = try!(decoder.build_fixed_visitor(byte, DICT_FIXED_START, DICT_FIXED_COUNT));
+            visit_entries(&mut visitor, &mut f)
+        }
+        _ => Err(Error::syntax("expected a dict")),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::decode;
+    use super::{decode, decode_map_streaming, decode_spans, decode_with_options, DecodeOptions, Error};
     use super::super::consts::*;
-    use std::collections::HashMap;
+    use super::super::value::Value;
+    use serde::de::{Deserialize, Deserializer, MapVisitor, SeqVisitor, Visitor};
+    use std::collections::{BTreeMap, HashMap};
+    use std::iter::repeat;
+    use std::{f64, i64, u64};
+
+    // What serde_macros derives for
+    //
+    //     struct Status {
+    //         name: String,
+    //         label: Option<String>,
+    //         #[serde(default)]
+    //         queue: i64,
+    //     }
+    #[derive(Debug, PartialEq)]
+    struct Status {
+        name: String,
+        label: Option<String>,
+        queue: i64,
+    }
+
+    struct StatusVisitor;
+
+    impl Visitor for StatusVisitor {
+
+        type Value = Status;
+
+        fn visit_map<V: MapVisitor>(&mut self, mut visitor: V) -> Result<Status, V::Error> {
+            let mut name = None;
+            let mut label = None;
+            let mut queue = None;
+            while let Some(key) = try!(visitor.visit_key::<String>()) {
+                match &key[..] {
+                    "name" => name = Some(try!(visitor.visit_value())),
+                    "label" => label = Some(try!(visitor.visit_value())),
+                    "queue" => queue = Some(try!(visitor.visit_value())),
+                    _ => {
+                        try!(visitor.visit_value::<Value>());
+                    }
+                }
+            }
+            try!(visitor.end());
+            Ok(Status {
+                name: match name {
+                    Some(name) => name,
+                    None => try!(visitor.missing_field("name")),
+                },
+                label: match label {
+                    Some(label) => label,
+                    None => try!(visitor.missing_field("label")),
+                },
+                queue: match queue {
+                    Some(queue) => queue,
+                    None => Default::default(),
+                },
+            })
+        }
+
+    }
+
+    impl Deserialize for Status {
+
+        fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Status, D::Error> {
+            deserializer.visit(StatusVisitor)
+        }
+
+    }
+
+    fn status(entries: Vec<(&str, Value)>) -> Vec<u8> {
+        Value::Dict(entries.into_iter().map(|(k, v)| (k.into(), v)).collect()).to_bytes()
+    }
+
+    // The size_hint reported when a list is visited.
+    struct Hint((usize, Option<usize>));
+
+    struct HintVisitor;
+
+    impl Visitor for HintVisitor {
+
+        type Value = Hint;
+
+        fn visit_seq<V: SeqVisitor>(&mut self, mut visitor: V) -> Result<Hint, V::Error> {
+            let hint = visitor.size_hint();
+            while let Some(_) = try!(visitor.visit::<Value>()) {}
+            try!(visitor.end());
+            Ok(Hint(hint))
+        }
+
+    }
+
+    impl Deserialize for Hint {
+
+        fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Hint, D::Error> {
+            deserializer.visit(HintVisitor)
+        }
+
+    }
 
     #[test]
     fn test_decode_string() {
@@ -416,6 +670,90 @@ mod tests {
         assert_eq!(n, -100);
     }
 
+    #[test]
+    fn test_decode_int_bounds() {
+        // embedded
+        let n: i8 = decode(&[0u8][..]).unwrap();
+        assert_eq!(n, 0);
+        let n: i8 = decode(&[70u8][..]).unwrap();
+        assert_eq!(n, -1);
+        // 44 is the F64 typecode, not an embedded int
+        assert!(decode::<i8, _>(&[44u8][..]).is_err());
+        // i8, i16, i32, i64
+        let n: i8 = decode(&[I8, 128][..]).unwrap();
+        assert_eq!(n, -128);
+        let n: i16 = decode(&[I16, 127, 255][..]).unwrap();
+        assert_eq!(n, 32767);
+        let n: i16 = decode(&[I16, 128, 0][..]).unwrap();
+        assert_eq!(n, -32768);
+        let n: i32 = decode(&[I32, 127, 255, 255, 255][..]).unwrap();
+        assert_eq!(n, 2147483647);
+        let n: i32 = decode(&[I32, 128, 0, 0, 0][..]).unwrap();
+        assert_eq!(n, -2147483648);
+        let n: i64 = decode(&[I64, 128, 0, 0, 0, 0, 0, 0, 0][..]).unwrap();
+        assert_eq!(n, i64::MIN);
+        // decimal string
+        let mut data = vec![INT];
+        data.extend(b"18446744073709551615");
+        data.push(TERM);
+        let n: u64 = decode(&data[..]).unwrap();
+        assert_eq!(n, u64::MAX);
+        let mut data = vec![INT];
+        data.extend(b"-9223372036854775808");
+        data.push(TERM);
+        let n: i64 = decode(&data[..]).unwrap();
+        assert_eq!(n, i64::MIN);
+        assert!(decode::<u64, _>(&[INT, b'1', b'x', TERM][..]).is_err());
+    }
+
+    #[test]
+    fn test_decode_string_bounds() {
+        let mut data = vec![191u8];
+        data.extend(repeat(b'a').take(63));
+        let s: String = decode(&data[..]).unwrap();
+        assert_eq!(s.len(), 63);
+        let mut data = b"64:".to_vec();
+        data.extend(repeat(b'a').take(64));
+        let s: String = decode(&data[..]).unwrap();
+        assert_eq!(s.len(), 64);
+    }
+
+    #[test]
+    fn test_decode_container_bounds() {
+        let mut data = vec![255u8];
+        data.extend(repeat(1).take(63));
+        let a: Vec<i8> = decode(&data[..]).unwrap();
+        assert_eq!(a.len(), 63);
+
+        let mut data = vec![126u8];
+        for i in 0..24 {
+            data.push(i);
+            data.push(i);
+        }
+        let a: HashMap<i8, i8> = decode(&data[..]).unwrap();
+        assert_eq!(a.len(), 24);
+        let mut data = vec![DICT];
+        for i in 0..25 {
+            data.push(i);
+            data.push(i);
+        }
+        data.push(TERM);
+        let a: HashMap<i8, i8> = decode(&data[..]).unwrap();
+        assert_eq!(a.len(), 25);
+    }
+
+    #[test]
+    fn test_decode_float_specials() {
+        let v: Value = decode(&[F64, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0][..]).unwrap();
+        assert!(v.approx_eq(&Value::F64(f64::NAN), 0.0));
+        let v: Value = decode(&[F32, 0x7f, 0xc0, 0, 0][..]).unwrap();
+        assert!(v.approx_eq(&Value::F64(f64::NAN), 0.0));
+        let n: f64 = decode(&[F64, 0x7f, 0xf0, 0, 0, 0, 0, 0, 0][..]).unwrap();
+        assert_eq!(n, f64::INFINITY);
+        let n: f64 = decode(&[F32, 0xff, 0x80, 0, 0][..]).unwrap();
+        assert_eq!(n, f64::NEG_INFINITY);
+    }
+
     #[test]
     fn test_decode_seq() {
         // embed
@@ -426,6 +764,14 @@ mod tests {
         assert_eq!(a, [1i8, 2, 3]);
     }
 
+    #[test]
+    fn test_decode_option() {
+        assert_eq!(decode::<Option<i8>, _>(&[1u8][..]).unwrap(), Some(1));
+        assert_eq!(decode::<Option<i8>, _>(&[NONE][..]).unwrap(), None);
+        let a: Vec<Option<String>> = decode(&[LIST, 129, b'a', NONE, TERM][..]).unwrap();
+        assert_eq!(a, [Some("a".to_string()), None]);
+    }
+
     #[test]
     fn test_decode_map() {
         let mut b = HashMap::new();
@@ -438,4 +784,150 @@ mod tests {
         let a: HashMap<i8, i8> = decode(&[DICT, I8, 1, I8, 2, I8, 3, I8, 4, TERM][..]).unwrap();
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn test_decode_map_keys() {
+        // file index keys
+        let a: HashMap<u32, String> = decode(&[104u8, 0, 129, b'a', 1, 129, b'b'][..]).unwrap();
+        assert_eq!(a[&0], "a");
+        assert_eq!(a[&1], "b");
+        let a: BTreeMap<i64, bool> = decode(&[DICT, I64, 0, 0, 0, 1, 0, 0, 0, 0, TRUE, 101, FALSE, TERM][..]).unwrap();
+        assert_eq!(a.into_iter().collect::<Vec<_>>(), [(-32, false), (4294967296, true)]);
+        // get_torrents_status, keyed by info hash
+        let hash = "c12fe1c06bba254a9dc9f519b335aa7c1367a88a";
+        let mut data = vec![103u8, 128 + hash.len() as u8];
+        data.extend(hash.as_bytes());
+        data.extend(&[104u8, 132, b'n', b'a', b'm', b'e', 131, b'a', b'b', b'c', 133, b'r', b'a', b't', b'i', b'o', F64,
+                      64, 6, 0, 0, 0, 0, 0, 0]);
+        let a: HashMap<String, HashMap<String, Value>> = decode(&data[..]).unwrap();
+        assert_eq!(a[hash]["name"], Value::String("abc".into()));
+        assert_eq!(a[hash]["ratio"], Value::F64(2.75));
+    }
+
+    #[test]
+    fn test_decode_skip_none_entries() {
+        let options = DecodeOptions {
+            skip_none_entries: true,
+        };
+        let data = [105u8, 129, b'a', 1, 129, b'b', NONE, 129, b'c', 2];
+        assert!(decode::<HashMap<String, i64>, _>(&data[..]).is_err());
+        let a: HashMap<String, i64> = decode_with_options(&data[..], options).unwrap();
+        assert_eq!(a.len(), 2);
+        assert_eq!(a["a"], 1);
+        assert_eq!(a["c"], 2);
+
+        let data = [DICT, 129, b'a', NONE, 129, b'b', I8, 100, TERM];
+        let a: HashMap<String, i64> = decode_with_options(&data[..], options).unwrap();
+        assert_eq!(a.into_iter().collect::<Vec<_>>(), [("b".to_string(), 100)]);
+
+        // values inside lists are left alone
+        let a: Vec<Option<i64>> = decode_with_options(&[194u8, NONE, 1][..], options).unwrap();
+        assert_eq!(a, [None, Some(1)]);
+    }
+
+    #[test]
+    fn test_decode_skip_none_struct() {
+        let options = DecodeOptions {
+            skip_none_entries: true,
+        };
+        let data = status(vec![("name", Value::String("a".into())),
+                               ("label", Value::None),
+                               ("queue", Value::None)]);
+        let expected = Status {
+            name: "a".into(),
+            label: None,
+            queue: 0,
+        };
+        assert_eq!(decode_with_options::<Status, _>(&data[..], options).unwrap(), expected);
+        // without the option, NONE is not a valid i64
+        assert!(decode::<Status, _>(&data[..]).is_err());
+
+        // absent Option fields are only None when the option is set
+        let data = status(vec![("name", Value::String("a".into()))]);
+        match decode::<Status, _>(&data[..]) {
+            Err(Error::MissingField("label")) => {}
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(decode_with_options::<Status, _>(&data[..], options).unwrap(), expected);
+
+        let data = status(vec![("name", Value::String("a".into())),
+                               ("label", Value::String("b".into()))]);
+        let a: Status = decode(&data[..]).unwrap();
+        assert_eq!(a.label, Some("b".into()));
+
+        let data = status(vec![("label", Value::String("b".into()))]);
+        match decode::<Status, _>(&data[..]) {
+            Err(Error::MissingField("name")) => {}
+            other => panic!("{:?}", other),
+        }
+        match decode_with_options::<Status, _>(&[DICT, 132, b'n', b'a', b'm', b'e', NONE, TERM][..], options) {
+            Err(Error::MissingField("name")) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_map_streaming() {
+        let mut entries = Vec::new();
+        decode_map_streaming(&[104u8, 1, 2, 3, 4][..], |k: i8, v: i8| entries.push((k, v))).unwrap();
+        assert_eq!(entries, [(1, 2), (3, 4)]);
+
+        let mut entries = Vec::new();
+        decode_map_streaming(&[DICT, 1, 129, b'a', 3, 129, b'b', TERM][..], |k: i8, v: String| {
+            entries.push((k, v))
+        }).unwrap();
+        assert_eq!(entries, [(1, "a".to_string()), (3, "b".to_string())]);
+
+        assert!(decode_map_streaming(&[195u8, 1, 2, 3][..], |_: i8, _: i8| {}).is_err());
+        assert!(decode_map_streaming(&[DICT, 1, 2][..], |_: i8, _: i8| {}).is_err());
+    }
+
+    #[test]
+    fn test_decode_size_hint() {
+        assert_eq!(decode::<Hint, _>(&[194, 1, 2][..]).unwrap().0, (2, Some(2)));
+        assert_eq!(decode::<Hint, _>(&[LIST, 1, 2, TERM][..]).unwrap().0, (0, None));
+    }
+
+    #[test]
+    fn test_decode_depth_limit() {
+        let mut data = repeat(LIST).take(MAX_DEPTH).collect::<Vec<u8>>();
+        data.extend(repeat(TERM).take(MAX_DEPTH));
+        assert!(decode::<Value, _>(&data[..]).is_ok());
+
+        for &open in &[LIST, 193u8] {
+            let data = repeat(open).take(10_000).collect::<Vec<u8>>();
+            match decode::<Value, _>(&data[..]) {
+                Err(Error::DepthLimitExceeded) => {}
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        // every typecode, alone and followed by a partial payload, must fail cleanly
+        for byte in 0..256u16 {
+            let byte = byte as u8;
+            let _ = decode::<Value, _>(&[byte][..]);
+            let _ = decode::<Value, _>(&[byte, 1][..]);
+            let _ = decode::<Value, _>(&[byte, b'9', b'9', b':'][..]);
+        }
+        assert!(decode::<Value, _>(&[I64, 0, 0][..]).is_err());
+        assert!(decode::<Value, _>(&[LIST, 1, 2][..]).is_err());
+        assert!(decode::<Value, _>(&[195u8, 1][..]).is_err());
+        assert!(decode::<Value, _>("5:abc".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_decode_spans() {
+        let data = [5, LIST, 1, 2, TERM, 131, 97, 98, 99, 194, NONE, 43];
+        let values = decode_spans::<Value>(&data).unwrap();
+        assert_eq!(values,
+                   vec![(Value::I64(5), 0..1),
+                        (Value::List(vec![Value::I64(1), Value::I64(2)]), 1..5),
+                        (Value::String("abc".into()), 5..9),
+                        (Value::List(vec![Value::None, Value::I64(43)]), 9..12)]);
+        assert!(decode_spans::<Value>(&[]).unwrap().is_empty());
+        assert!(decode_spans::<Value>(&[5, LIST, 1]).is_err());
+    }
 }
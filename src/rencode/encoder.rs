@@ -1,42 +1,10 @@
 use byteorder::{self, BigEndian, WriteBytesExt};
 use serde::ser::{MapVisitor, SeqVisitor, Serialize, Serializer};
-use std::{i8, i16, i32};
 use std::io::{self, Write};
 
+use super::codec;
 use super::consts::*;
 
-// i8 bounds
-pub const I8_MIN: i64 = i8::MIN as i64;
-pub const I8_MAX: i64 = i8::MAX as i64;
-
-// i16 bounds
-pub const I16_MIN: i64 = i16::MIN as i64;
-pub const I16_MAX: i64 = i16::MAX as i64;
-
-// i32 bounds
-pub const I32_MIN: i64 = i32::MIN as i64;
-pub const I32_MAX: i64 = i32::MAX as i64;
-
-// positive integers with value embedded in typecode.
-const INT_POS_FIXED_START: i64 = 0;
-const INT_POS_FIXED_COUNT: i64 = 44;
-
-// Negative integers with value embedded in typecode.
-const INT_NEG_FIXED_START: i64 = 70;
-const INT_NEG_FIXED_COUNT: i64 = -32;
-
-// Strings with length embedded in typecode.
-const STR_FIXED_START: u8 = 128;
-const STR_FIXED_COUNT: u8 = 64;
-
-// Lists with length embedded in typecode.
-const LIST_FIXED_START: u8 = STR_FIXED_START + STR_FIXED_COUNT;
-const LIST_FIXED_COUNT: u8 = 64;
-
-// Dictionaries with length embedded in typecode.
-const DICT_FIXED_START: u8 = 102;
-const DICT_FIXED_COUNT: u8 = 25;
-
 #[derive(Debug)]
 pub enum Error {
     IoError(io::Error),
@@ -60,8 +28,29 @@ impl From<io::Error> for Error {
     }
 }
 
+/// Options controlling which encodings the encoder picks.
+#[derive(Clone, Copy, Debug)]
+pub struct EncodeOptions {
+    /// Embed the length of short strings, lists and dicts in their typecode. When false,
+    /// strings are always length-prefixed and lists and dicts always use LIST/DICT with a
+    /// TERM, like the reference implementation does for values over the fixed limits.
+    /// Integers are not affected.
+    pub use_fixed_types: bool,
+}
+
+impl Default for EncodeOptions {
+
+    fn default() -> EncodeOptions {
+        EncodeOptions {
+            use_fixed_types: true,
+        }
+    }
+
+}
+
 struct Encoder<W: Write> {
     writer: W,
+    options: EncodeOptions,
 }
 
 impl<W: Write> Serializer for Encoder<W> {
@@ -75,35 +64,13 @@ impl<W: Write> Serializer for Encoder<W> {
     }
 
     fn visit_i64(&mut self, v: i64) -> Result<(), Error> {
-        match v {
-            INT_NEG_FIXED_COUNT...-1 => {
-                try!(self.writer.write_i8(INT_NEG_FIXED_START as i8 - 1 - v as i8));
-            }
-            0...INT_POS_FIXED_COUNT => {
-                try!(self.writer.write_u8(INT_POS_FIXED_START as u8 + v as u8));
-            }
-            I8_MIN...I8_MAX => {
-                try!(self.writer.write_u8(I8));
-                try!(self.writer.write_i8(v as i8));
-            }
-            I16_MIN...I16_MAX => {
-                try!(self.writer.write_u8(I16));
-                try!(self.writer.write_i16::<BigEndian>(v as i16));
-            }
-            I32_MIN...I32_MAX => {
-                try!(self.writer.write_u8(I32));
-                try!(self.writer.write_i32::<BigEndian>(v as i32));
-            }
-            _ => {
-                try!(self.writer.write_u8(I64));
-                try!(self.writer.write_i64::<BigEndian>(v));
-            }
-        }
+        try!(codec::write_i64(&mut self.writer, v));
         Ok(())
     }
 
     fn visit_u64(&mut self, v: u64) -> Result<(), Error> {
-        self.visit_i64(v as i64)
+        try!(codec::write_u64(&mut self.writer, v));
+        Ok(())
     }
 
     fn visit_f32(&mut self, v: f32) -> Result<(), Error> {
@@ -113,8 +80,7 @@ impl<W: Write> Serializer for Encoder<W> {
     }
 
     fn visit_f64(&mut self, v: f64) -> Result<(), Error> {
-        try!(self.writer.write_u8(F64));
-        try!(self.writer.write_f64::<BigEndian>(v));
+        try!(codec::write_f64(&mut self.writer, v));
         Ok(())
     }
 
@@ -132,29 +98,18 @@ impl<W: Write> Serializer for Encoder<W> {
     }
 
     fn visit_str(&mut self, v: &str) -> Result<(), Error> {
-        if v.len() < STR_FIXED_COUNT as usize {
-            try!(self.writer.write_u8(STR_FIXED_START + v.len() as u8));
-            try!(self.writer.write_all(v.as_bytes()));
-        } else {
-            try!(write!(self.writer, "{}:{}", v.len(), v));
-        }
+        try!(codec::write_str(&mut self.writer, v, self.options.use_fixed_types));
         Ok(())
     }
 
     fn visit_seq<V: SeqVisitor>(&mut self, mut v: V) -> Result<(), Error> {
-        match v.len() {
-            Some(len) if len < LIST_FIXED_COUNT as usize => {
-                try!(self.writer.write_u8(LIST_FIXED_START + len as u8));
-                while let Some(_) = try!(v.visit(self)) {}
-                return Ok(());
-            }
-            Some(_) | None => {
-                try!(self.writer.write_u8(LIST));
-                while let Some(_) = try!(v.visit(self)) {}
-                try!(self.writer.write_u8(TERM));
-                Ok(())
-            }
+        let len = if self.options.use_fixed_types { v.len() } else { None };
+        let term = try!(codec::write_list_start(&mut self.writer, len));
+        while let Some(_) = try!(v.visit(self)) {}
+        if term {
+            try!(self.writer.write_u8(TERM));
         }
+        Ok(())
     }
 
     fn visit_seq_elt<V: Serialize>(&mut self, v: V) -> Result<(), Error> {
@@ -162,19 +117,13 @@ impl<W: Write> Serializer for Encoder<W> {
     }
 
     fn visit_map<V: MapVisitor>(&mut self, mut v: V) -> Result<(), Error> {
-        match v.len() {
-            Some(len) if len < DICT_FIXED_COUNT as usize => {
-                try!(self.writer.write_u8(DICT_FIXED_START + len as u8));
-                while let Some(_) = try!(v.visit(self)) {}
-                return Ok(());
-            }
-            Some(_) | None => {
-                try!(self.writer.write_u8(DICT));
-                while let Some(_) = try!(v.visit(self)) {}
-                try!(self.writer.write_u8(TERM));
-                Ok(())
-            }
+        let len = if self.options.use_fixed_types { v.len() } else { None };
+        let term = try!(codec::write_dict_start(&mut self.writer, len));
+        while let Some(_) = try!(v.visit(self)) {}
+        if term {
+            try!(self.writer.write_u8(TERM));
         }
+        Ok(())
     }
 
     fn visit_map_elt<K: Serialize, V: Serialize>(&mut self, k: K, v: V) -> Result<(), Error> {
@@ -186,24 +135,109 @@ impl<W: Write> Serializer for Encoder<W> {
 }
 
 pub fn encode<S: Serialize>(v: S) -> Result<Vec<u8>, Error> {
+    encode_with_options(v, EncodeOptions::default())
+}
+
+pub fn encode_with_options<S: Serialize>(v: S, options: EncodeOptions) -> Result<Vec<u8>, Error> {
     let mut encoder = Encoder {
         writer: Vec::new(),
+        options: options,
     };
     try!(v.serialize(&mut encoder));
     Ok((encoder.writer))
 }
 
+/// Writes a list one element at a time, for when the number of elements isn't known up front.
+///
+/// The list is only terminated by `finish`, dropping the writer leaves it incomplete.
+pub struct ListWriter<W: Write> {
+    writer: W,
+    options: EncodeOptions,
+}
+
+impl<W: Write> ListWriter<W> {
+
+    pub fn new(writer: W) -> Result<ListWriter<W>, Error> {
+        ListWriter::new_with_options(writer, EncodeOptions::default())
+    }
+
+    /// Like `new`, encoding the elements with `options`.
+    pub fn new_with_options(mut writer: W, options: EncodeOptions) -> Result<ListWriter<W>, Error> {
+        try!(writer.write_u8(LIST));
+        Ok(ListWriter {
+            writer: writer,
+            options: options,
+        })
+    }
+
+    pub fn push<S: Serialize>(&mut self, v: S) -> Result<(), Error> {
+        v.serialize(&mut Encoder {
+            writer: &mut self.writer,
+            options: self.options,
+        })
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        try!(self.writer.write_u8(TERM));
+        Ok(self.writer)
+    }
+
+}
+
+/// Writes a dict one entry at a time, for when the number of entries isn't known up front.
+///
+/// The dict is only terminated by `finish`, dropping the writer leaves it incomplete.
+pub struct DictWriter<W: Write> {
+    writer: W,
+    options: EncodeOptions,
+}
+
+impl<W: Write> DictWriter<W> {
+
+    pub fn new(writer: W) -> Result<DictWriter<W>, Error> {
+        DictWriter::new_with_options(writer, EncodeOptions::default())
+    }
+
+    /// Like `new`, encoding the keys and values with `options`.
+    pub fn new_with_options(mut writer: W, options: EncodeOptions) -> Result<DictWriter<W>, Error> {
+        try!(writer.write_u8(DICT));
+        Ok(DictWriter {
+            writer: writer,
+            options: options,
+        })
+    }
+
+    pub fn insert<K: Serialize, V: Serialize>(&mut self, k: K, v: V) -> Result<(), Error> {
+        let mut encoder = Encoder {
+            writer: &mut self.writer,
+            options: self.options,
+        };
+        try!(k.serialize(&mut encoder));
+        try!(v.serialize(&mut encoder));
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, Error> {
+        try!(self.writer.write_u8(TERM));
+        Ok(self.writer)
+    }
+
+}
+
 #[cfg(test)]
 mod tests {
-    use super::encode;
-    use super::super::consts::{DICT, LIST, TERM};
+    use super::{encode, encode_with_options, DictWriter, EncodeOptions, ListWriter};
+    use super::super::consts::{DICT, INT, LIST, TERM};
     use std::collections::HashMap;
     use std::iter::repeat;
+    use std::{f64, i64, u64};
 
     #[test]
     fn test_encode_int() {
         // integers
         assert_eq!(encode(5).unwrap(), &[5]);
+        assert_eq!(encode(43).unwrap(), &[43]);
+        assert_eq!(encode(44).unwrap(), &[62, 44]);
         assert_eq!(encode(-5).unwrap(), &[74]);
         assert_eq!(encode(100).unwrap(), &[62, 100]);
         assert_eq!(encode(-100).unwrap(), &[62, 156]);
@@ -215,6 +249,47 @@ mod tests {
         assert_eq!(encode(-400_000_000_000_i64).unwrap(), &[65, 255, 255, 255, 162, 222, 36, 96, 0]);
     }
 
+    #[test]
+    fn test_encode_int_bounds() {
+        // embedded
+        assert_eq!(encode(0).unwrap(), &[0]);
+        assert_eq!(encode(-1).unwrap(), &[70]);
+        assert_eq!(encode(-32).unwrap(), &[101]);
+        assert_eq!(encode(-33).unwrap(), &[62, 223]);
+        // i8
+        assert_eq!(encode(127).unwrap(), &[62, 127]);
+        assert_eq!(encode(-128).unwrap(), &[62, 128]);
+        // i16
+        assert_eq!(encode(128).unwrap(), &[63, 0, 128]);
+        assert_eq!(encode(-129).unwrap(), &[63, 255, 127]);
+        assert_eq!(encode(32767).unwrap(), &[63, 127, 255]);
+        assert_eq!(encode(-32768).unwrap(), &[63, 128, 0]);
+        // i32
+        assert_eq!(encode(32768).unwrap(), &[64, 0, 0, 128, 0]);
+        assert_eq!(encode(-32769).unwrap(), &[64, 255, 255, 127, 255]);
+        assert_eq!(encode(2147483647).unwrap(), &[64, 127, 255, 255, 255]);
+        assert_eq!(encode(-2147483648).unwrap(), &[64, 128, 0, 0, 0]);
+        // i64
+        assert_eq!(encode(2147483648_i64).unwrap(), &[65, 0, 0, 0, 0, 128, 0, 0, 0]);
+        assert_eq!(encode(i64::MAX).unwrap(), &[65, 127, 255, 255, 255, 255, 255, 255, 255]);
+        assert_eq!(encode(i64::MIN).unwrap(), &[65, 128, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encode(i64::MAX as u64).unwrap(), &[65, 127, 255, 255, 255, 255, 255, 255, 255]);
+        // decimal string
+        let mut expected = vec![INT];
+        expected.extend(b"18446744073709551615");
+        expected.push(TERM);
+        assert_eq!(encode(u64::MAX).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_encode_float_specials() {
+        assert_eq!(encode(f64::INFINITY).unwrap(), &[44, 0x7f, 0xf0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(encode(f64::NEG_INFINITY).unwrap(), &[44, 0xff, 0xf0, 0, 0, 0, 0, 0, 0]);
+        let data = encode(f64::NAN).unwrap();
+        assert_eq!(data[0], 44);
+        assert_eq!(data[1] & 0x7f, 0x7f);
+    }
+
     #[test]
     fn test_encode_string() {
         assert_eq!(encode("abc").unwrap(), &[131, 97, 98, 99]);
@@ -222,6 +297,16 @@ mod tests {
                    "80:ghkdgdfjgdfjgfdgjhkdfgjhdfgfdjgdfjkgdfjhghfdgdfhkgdfhkgfdhgdfhgdfhdfghdfghkdfhdk".as_bytes());
     }
 
+    #[test]
+    fn test_encode_string_bounds() {
+        let data = encode(repeat('a').take(63).collect::<String>()).unwrap();
+        assert_eq!(data.len(), 64);
+        assert_eq!(data[0], 191);
+        let data = encode(repeat('a').take(64).collect::<String>()).unwrap();
+        assert_eq!(data.len(), 67);
+        assert_eq!(&data[..3], b"64:");
+    }
+
     #[test]
     fn test_encode_list() {
         assert_eq!(encode(&[1, 2]).unwrap(), &[194, 1, 2]);
@@ -246,4 +331,64 @@ mod tests {
         assert_eq!(data[0], DICT);
         assert_eq!(data.last(), Some(TERM).as_ref());
     }
+
+    #[test]
+    fn test_encode_container_bounds() {
+        let data = encode(repeat(1).take(63).collect::<Vec<u8>>()).unwrap();
+        assert_eq!(data.len(), 64);
+        assert_eq!(data[0], 255);
+        let data = encode(repeat(1).take(64).collect::<Vec<u8>>()).unwrap();
+        assert_eq!(data.len(), 66);
+        assert_eq!(data[0], LIST);
+
+        let map = (0..24).map(|i| (i, i)).collect::<HashMap<u8, u8>>();
+        let data = encode(map).unwrap();
+        assert_eq!(data.len(), 49);
+        assert_eq!(data[0], 126);
+        let map = (0..25).map(|i| (i, i)).collect::<HashMap<u8, u8>>();
+        let data = encode(map).unwrap();
+        assert_eq!(data.len(), 52);
+        assert_eq!(data[0], DICT);
+    }
+
+    #[test]
+    fn test_list_writer() {
+        let mut list = ListWriter::new(Vec::new()).unwrap();
+        list.push(1).unwrap();
+        list.push("a").unwrap();
+        assert_eq!(list.finish().unwrap(), &[LIST, 1, 129, 97, TERM]);
+        let list = ListWriter::new(Vec::new()).unwrap();
+        assert_eq!(list.finish().unwrap(), &[LIST, TERM]);
+    }
+
+    #[test]
+    fn test_dict_writer() {
+        let mut dict = DictWriter::new(Vec::new()).unwrap();
+        dict.insert("a", 1).unwrap();
+        dict.insert("b", &[2, 3]).unwrap();
+        assert_eq!(dict.finish().unwrap(), &[DICT, 129, 97, 1, 129, 98, 194, 2, 3, TERM]);
+    }
+
+    #[test]
+    fn test_encode_no_fixed_types() {
+        let options = EncodeOptions {
+            use_fixed_types: false,
+        };
+        assert_eq!(encode_with_options("abc", options).unwrap(), b"3:abc");
+        assert_eq!(encode_with_options("", options).unwrap(), b"0:");
+        assert_eq!(encode_with_options(&[1, 2], options).unwrap(), &[LIST, 1, 2, TERM]);
+        let mut map = HashMap::new();
+        map.insert("a", vec![5]);
+        assert_eq!(encode_with_options(map, options).unwrap(), &[DICT, b'1', b':', b'a', LIST, 5, TERM, TERM]);
+        // integers keep their compact encodings
+        assert_eq!(encode_with_options(5, options).unwrap(), &[5]);
+
+        let mut list = ListWriter::new_with_options(Vec::new(), options).unwrap();
+        list.push("a").unwrap();
+        list.push(&[1]).unwrap();
+        assert_eq!(list.finish().unwrap(), &[LIST, b'1', b':', b'a', LIST, 1, TERM, TERM]);
+        let mut dict = DictWriter::new_with_options(Vec::new(), options).unwrap();
+        dict.insert("a", "b").unwrap();
+        assert_eq!(dict.finish().unwrap(), &[DICT, b'1', b':', b'a', b'1', b':', b'b', TERM]);
+    }
 }
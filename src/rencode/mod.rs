@@ -1,8 +1,15 @@
+mod codec;
 mod consts;
+#[cfg(feature = "serde")]
 mod decoder;
+#[cfg(feature = "serde")]
 mod encoder;
 mod value;
 
-pub use self::decoder::{decode, Error as DecoderError};
-pub use self::encoder::{encode, Error as EncoderError};
-pub use self::value::Value;
+pub use self::codec::Error as ParseError;
+#[cfg(feature = "serde")]
+pub use self::decoder::{decode, decode_map_streaming, decode_spans, decode_with_options, DecodeOptions,
+                        Error as DecoderError};
+#[cfg(feature = "serde")]
+pub use self::encoder::{encode, encode_with_options, DictWriter, EncodeOptions, Error as EncoderError, ListWriter};
+pub use self::value::{MergeStrategy, Value};
@@ -1,9 +1,17 @@
+#[cfg(feature = "serde")]
 use serde::de::{self, Deserialize, Deserializer, Error};
+#[cfg(feature = "serde")]
 use serde::ser::{self, Serialize, Serializer};
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+#[cfg(feature = "serde")]
 use std::collections::btree_map;
+#[cfg(feature = "serde")]
 use std::slice;
 
+use super::codec;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     None,
@@ -16,6 +24,212 @@ pub enum Value {
     Dict(BTreeMap<String, Value>),
 }
 
+/// Decides what `Value::merge` does when both values have something at the same place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MergeStrategy {
+    /// Keep the existing value.
+    Keep,
+    /// Replace the existing value with the new one.
+    Replace,
+    /// Like `Replace`, but lists are concatenated.
+    Append,
+}
+
+impl Value {
+
+    /// Parses a single value from `data` without going through serde.
+    ///
+    /// Trailing bytes after the value are an error.
+    pub fn parse(data: &[u8]) -> Result<Value, codec::Error> {
+        codec::parse(data)
+    }
+
+    /// Encodes the value without going through serde.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        // writing to a Vec can't fail
+        codec::write(&mut out, self).unwrap();
+        out
+    }
+
+    /// Returns the map if the value is a `Dict`.
+    pub fn as_dict(&self) -> Option<&BTreeMap<String, Value>> {
+        match *self {
+            Value::Dict(ref map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Returns the map if the value is a `Dict`.
+    pub fn as_dict_mut(&mut self) -> Option<&mut BTreeMap<String, Value>> {
+        match *self {
+            Value::Dict(ref mut map) => Some(map),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if the value is a `List`.
+    pub fn as_list(&self) -> Option<&Vec<Value>> {
+        match *self {
+            Value::List(ref list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements if the value is a `List`.
+    pub fn as_list_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::List(ref mut list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Inserts a key in a dict, returning the previous value.
+    ///
+    /// If the value is not a `Dict`, nothing is inserted and `value` is given back as an error.
+    pub fn insert<K: Into<String>>(&mut self, key: K, value: Value) -> Result<Option<Value>, Value> {
+        match self.as_dict_mut() {
+            Some(map) => Ok(map.insert(key.into(), value)),
+            None => Err(value),
+        }
+    }
+
+    /// Removes a key from a dict, returning its value. Returns `None` if the value is not a `Dict`.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.as_dict_mut().and_then(|map| map.remove(key))
+    }
+
+    /// Gets the entry of a key in a dict. Returns `None` if the value is not a `Dict`.
+    pub fn entry<K: Into<String>>(&mut self, key: K) -> Option<Entry<'_, String, Value>> {
+        self.as_dict_mut().map(|map| map.entry(key.into()))
+    }
+
+    /// Appends a value to a list.
+    ///
+    /// If the value is not a `List`, nothing is appended and `value` is given back as an error.
+    pub fn push(&mut self, value: Value) -> Result<(), Value> {
+        match self.as_list_mut() {
+            Some(list) => {
+                list.push(value);
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Looks up a value using a JSON Pointer (RFC 6901) such as `/torrents/<hash>/files/0/path`.
+    /// Dict keys are matched exactly and list elements are selected by index.
+    pub fn pointer<'a>(&'a self, pointer: &str) -> Option<&'a Value> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        let mut target = self;
+        for token in pointer.split('/').skip(1) {
+            let token = unescape_token(token);
+            target = match *target {
+                Value::Dict(ref map) => match map.get(&token) {
+                    Some(value) => value,
+                    None => return None,
+                },
+                Value::List(ref list) => match parse_index(&token).and_then(|i| list.get(i)) {
+                    Some(value) => value,
+                    None => return None,
+                },
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Same as `pointer`, but returns a mutable reference.
+    pub fn pointer_mut<'a>(&'a mut self, pointer: &str) -> Option<&'a mut Value> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        let mut target = self;
+        for token in pointer.split('/').skip(1) {
+            let token = unescape_token(token);
+            let target_once = target;
+            target = match *target_once {
+                Value::Dict(ref mut map) => match map.get_mut(&token) {
+                    Some(value) => value,
+                    None => return None,
+                },
+                Value::List(ref mut list) => match parse_index(&token).and_then(move |i| list.get_mut(i)) {
+                    Some(value) => value,
+                    None => return None,
+                },
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Compares two values, allowing floats to differ by at most `epsilon`. Unlike `==`, NaN is
+    /// considered equal to NaN.
+    pub fn approx_eq(&self, other: &Value, epsilon: f64) -> bool {
+        match (self, other) {
+            (&Value::F64(a), &Value::F64(b)) => {
+                (a.is_nan() && b.is_nan()) || a == b || (a - b).abs() <= epsilon
+            }
+            (&Value::List(ref a), &Value::List(ref b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+            }
+            (&Value::Dict(ref a), &Value::Dict(ref b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|((ka, a), (kb, b))| ka == kb && a.approx_eq(b, epsilon))
+            }
+            _ => self == other,
+        }
+    }
+
+    /// Orders two floats using the IEEE 754 total order, where -0.0 sorts before 0.0 and NaN
+    /// sorts by its sign: positive NaN after infinity, negative NaN before -infinity. Returns
+    /// `None` unless both values are `F64`.
+    pub fn total_cmp(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (&Value::F64(a), &Value::F64(b)) => Some(total_order_key(a).cmp(&total_order_key(b))),
+            _ => None,
+        }
+    }
+
+    /// Merges `other` into this value. Dicts are merged recursively, everything else is resolved
+    /// using the strategy.
+    pub fn merge(&mut self, other: Value, strategy: MergeStrategy) {
+        match (self, other) {
+            (&mut Value::Dict(ref mut map), Value::Dict(other)) => {
+                for (key, value) in other {
+                    match map.entry(key) {
+                        Entry::Occupied(mut entry) => entry.get_mut().merge(value, strategy),
+                        Entry::Vacant(entry) => {
+                            entry.insert(value);
+                        }
+                    }
+                }
+            }
+            (&mut Value::List(ref mut list), Value::List(other)) => {
+                match strategy {
+                    MergeStrategy::Keep => {}
+                    MergeStrategy::Replace => *list = other,
+                    MergeStrategy::Append => list.extend(other),
+                }
+            }
+            (this, other) => {
+                if strategy != MergeStrategy::Keep {
+                    *this = other;
+                }
+            }
+        }
+    }
+
+}
+
+#[cfg(feature = "serde")]
 impl Serialize for Value {
 
     fn serialize<S: Serializer>(&self, serializer: &mut S) -> Result<(), S::Error> {
@@ -38,11 +252,32 @@ impl Serialize for Value {
     }
 }
 
+// flips the bits of negative floats so that their representation sorts as a signed integer
+fn total_order_key(v: f64) -> i64 {
+    let bits = v.to_bits() as i64;
+    bits ^ ((((bits >> 63) as u64) >> 1) as i64)
+}
+
+// "~1" stands for "/" and "~0" for "~", in that order
+fn unescape_token(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+// list indices are plain decimal numbers without leading zeros
+fn parse_index(token: &str) -> Option<usize> {
+    if token.starts_with('+') || (token.starts_with('0') && token.len() > 1) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+#[cfg(feature = "serde")]
 struct SeqSerializer<'a> {
     iter: slice::Iter<'a, Value>,
     len: usize,
 }
 
+#[cfg(feature = "serde")]
 impl<'a> ser::SeqVisitor for SeqSerializer<'a> {
 
     fn visit<S: Serializer>(&mut self, serializer: &mut S) -> Result<Option<()>, S::Error> {
@@ -61,11 +296,13 @@ impl<'a> ser::SeqVisitor for SeqSerializer<'a> {
 
 }
 
+#[cfg(feature = "serde")]
 struct MapSerializer<'a> {
     iter: btree_map::Iter<'a, String, Value>,
     len: usize,
 }
 
+#[cfg(feature = "serde")]
 impl<'a> ser::MapVisitor for MapSerializer<'a> {
 
     fn visit<S: Serializer>(&mut self, serializer: &mut S) -> Result<Option<()>, S::Error> {
@@ -84,8 +321,10 @@ impl<'a> ser::MapVisitor for MapSerializer<'a> {
 
 }
 
+#[cfg(feature = "serde")]
 struct ValueVisitor;
 
+#[cfg(feature = "serde")]
 impl de::Visitor for ValueVisitor {
 
     type Value = Value;
@@ -130,6 +369,7 @@ impl de::Visitor for ValueVisitor {
 
 }
 
+#[cfg(feature = "serde")]
 impl Deserialize for Value {
 
     fn deserialize<D: Deserializer>(deserializer: &mut D) -> Result<Value, D::Error> {
@@ -137,3 +377,121 @@ impl Deserialize for Value {
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeStrategy, Value};
+    use std::cmp::Ordering;
+    use std::collections::BTreeMap;
+    use std::f64;
+
+    fn dict(pairs: Vec<(&str, Value)>) -> Value {
+        Value::Dict(pairs.into_iter().map(|(k, v)| (k.into(), v)).collect::<BTreeMap<_, _>>())
+    }
+
+    #[test]
+    fn test_value_dict_mutation() {
+        let mut v = dict(vec![]);
+        assert_eq!(v.insert("a", Value::I64(1)), Ok(None));
+        assert_eq!(v.insert("a", Value::I64(2)), Ok(Some(Value::I64(1))));
+        v.entry("b").unwrap().or_insert(Value::List(vec![])).push(Value::Bool(true)).unwrap();
+        assert_eq!(v.remove("a"), Some(Value::I64(2)));
+        assert_eq!(v.remove("a"), None);
+        assert_eq!(v, dict(vec![("b", Value::List(vec![Value::Bool(true)]))]));
+        assert_eq!(v.as_dict().map(|map| map.len()), Some(1));
+        assert_eq!(v.as_list(), None);
+    }
+
+    #[test]
+    fn test_value_mutation_wrong_variant() {
+        let mut v = Value::None;
+        assert_eq!(v.insert("a", Value::I64(1)), Err(Value::I64(1)));
+        assert_eq!(v.remove("a"), None);
+        assert!(v.entry("a").is_none());
+        assert_eq!(v.push(Value::I64(2)), Err(Value::I64(2)));
+        assert_eq!(dict(vec![]).push(Value::I64(3)), Err(Value::I64(3)));
+        assert_eq!(Value::List(vec![]).insert("a", Value::I64(4)), Err(Value::I64(4)));
+        assert_eq!(v, Value::None);
+    }
+
+    #[test]
+    fn test_value_pointer() {
+        let mut v = dict(vec![
+            ("files", Value::List(vec![dict(vec![("path", Value::String("a/b".into()))])])),
+            ("a/b", Value::I64(1)),
+            ("m~n", Value::I64(2)),
+        ]);
+        assert_eq!(v.pointer(""), Some(&v.clone()));
+        assert_eq!(v.pointer("/files/0/path"), Some(&Value::String("a/b".into())));
+        assert_eq!(v.pointer("/a~1b"), Some(&Value::I64(1)));
+        assert_eq!(v.pointer("/m~0n"), Some(&Value::I64(2)));
+        assert_eq!(v.pointer("/files/1"), None);
+        assert_eq!(v.pointer("/files/00"), None);
+        assert_eq!(v.pointer("/files/0/path/x"), None);
+        assert_eq!(v.pointer("files"), None);
+
+        *v.pointer_mut("/files/0/path").unwrap() = Value::None;
+        assert_eq!(v.pointer("/files/0/path"), Some(&Value::None));
+        assert!(v.pointer_mut("/nope").is_none());
+    }
+
+    #[test]
+    fn test_value_approx_eq() {
+        let nan = Value::F64(f64::NAN);
+        assert!(nan.approx_eq(&nan, 0.0));
+        assert!(Value::F64(f64::INFINITY).approx_eq(&Value::F64(f64::INFINITY), 0.0));
+        assert!(!Value::F64(f64::INFINITY).approx_eq(&Value::F64(f64::NEG_INFINITY), 1.0));
+        assert!(Value::F64(0.1 + 0.2).approx_eq(&Value::F64(0.3), 1e-9));
+        assert!(!Value::F64(0.1).approx_eq(&Value::F64(0.3), 1e-9));
+        assert!(dict(vec![("ratio", nan.clone())]).approx_eq(&dict(vec![("ratio", nan.clone())]), 0.0));
+        assert!(!Value::List(vec![nan.clone()]).approx_eq(&Value::List(vec![]), 0.0));
+        assert!(!Value::I64(1).approx_eq(&Value::F64(1.0), 0.0));
+    }
+
+    #[test]
+    fn test_value_total_cmp() {
+        let cmp = |a: f64, b: f64| Value::F64(a).total_cmp(&Value::F64(b));
+        assert_eq!(cmp(f64::NAN, f64::NAN), Some(Ordering::Equal));
+        assert_eq!(cmp(f64::INFINITY, f64::NAN), Some(Ordering::Less));
+        assert_eq!(cmp(-f64::NAN, f64::NEG_INFINITY), Some(Ordering::Less));
+        assert_eq!(cmp(-f64::NAN, f64::NAN), Some(Ordering::Less));
+        assert_eq!(cmp(f64::NEG_INFINITY, -1.0), Some(Ordering::Less));
+        assert_eq!(cmp(-0.0, 0.0), Some(Ordering::Less));
+        assert_eq!(cmp(2.5, 1.5), Some(Ordering::Greater));
+        assert_eq!(Value::I64(1).total_cmp(&Value::F64(1.0)), None);
+    }
+
+    #[test]
+    fn test_value_merge() {
+        let base = dict(vec![
+            ("a", Value::I64(1)),
+            ("l", Value::List(vec![Value::I64(1)])),
+            ("d", dict(vec![("x", Value::I64(1)), ("y", Value::I64(1))])),
+        ]);
+        let other = dict(vec![
+            ("a", Value::I64(2)),
+            ("l", Value::List(vec![Value::I64(2)])),
+            ("d", dict(vec![("y", Value::I64(2)), ("z", Value::I64(2))])),
+        ]);
+
+        let mut v = base.clone();
+        v.merge(other.clone(), MergeStrategy::Keep);
+        assert_eq!(v, dict(vec![
+            ("a", Value::I64(1)),
+            ("l", Value::List(vec![Value::I64(1)])),
+            ("d", dict(vec![("x", Value::I64(1)), ("y", Value::I64(1)), ("z", Value::I64(2))])),
+        ]));
+
+        let mut v = base.clone();
+        v.merge(other.clone(), MergeStrategy::Replace);
+        assert_eq!(v, dict(vec![
+            ("a", Value::I64(2)),
+            ("l", Value::List(vec![Value::I64(2)])),
+            ("d", dict(vec![("x", Value::I64(1)), ("y", Value::I64(2)), ("z", Value::I64(2))])),
+        ]));
+
+        let mut v = base.clone();
+        v.merge(other, MergeStrategy::Append);
+        assert_eq!(v.remove("l"), Some(Value::List(vec![Value::I64(1), Value::I64(2)])));
+    }
+}
@@ -0,0 +1,270 @@
+//! Sizes, transfer rates, share ratios and durations, displayed in human units.
+
+use std::fmt;
+use std::num::ParseFloatError;
+use std::str::FromStr;
+use std::time::Duration;
+
+const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+#[derive(Debug, PartialEq)]
+pub enum ParseError {
+    OutOfRange,
+    ParseFloatError(ParseFloatError),
+    UnknownUnit(String),
+}
+
+impl From<ParseFloatError> for ParseError {
+    fn from(err: ParseFloatError) -> ParseError {
+        ParseError::ParseFloatError(err)
+    }
+}
+
+// Writes `n` in the largest binary unit that keeps it at or above 1, with one decimal that is
+// left out when it's 0: "512 B", "1.4 GiB", "250 KiB/s".
+fn fmt_binary(f: &mut fmt::Formatter, n: u64, suffix: &str) -> fmt::Result {
+    let mut value = n as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    let number = format!("{:.1}", value);
+    let number = number.strip_suffix(".0").unwrap_or(&number);
+    f.pad(&format!("{} {}{}", number, UNITS[unit], suffix))
+}
+
+// Parses a number of bytes followed by an optional binary unit and `suffix`. Units are case
+// insensitive and the result is rounded to the nearest byte.
+fn parse_binary(s: &str, suffix: &str) -> Result<u64, ParseError> {
+    let s = s.trim();
+    let s = s.strip_suffix(suffix).unwrap_or(s);
+    let (number, unit) = s.split_at(s.find(char::is_alphabetic).unwrap_or(s.len()));
+    let number: f64 = try!(number.trim().parse());
+    let exponent = match unit {
+        "" => 0,
+        unit => match UNITS.iter().position(|u| u.eq_ignore_ascii_case(unit)) {
+            Some(exponent) => exponent,
+            None => return Err(ParseError::UnknownUnit(unit.into())),
+        },
+    };
+    let bytes = (number * 1024f64.powi(exponent as i32)).round();
+    // 2^64, the first value past u64::MAX
+    if (0.0..18446744073709551616.0).contains(&bytes) {
+        Ok(bytes as u64)
+    } else {
+        Err(ParseError::OutOfRange)
+    }
+}
+
+/// A size in bytes, such as `total_size` or `total_done`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Bytes(pub u64);
+
+impl From<u64> for Bytes {
+    fn from(n: u64) -> Bytes {
+        Bytes(n)
+    }
+}
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_binary(f, self.0, "")
+    }
+}
+
+impl FromStr for Bytes {
+
+    type Err = ParseError;
+
+    /// Parses sizes like "1.4 GiB", "512 B" or "2048".
+    fn from_str(s: &str) -> Result<Bytes, ParseError> {
+        parse_binary(s, "").map(Bytes)
+    }
+
+}
+
+/// A transfer rate in bytes per second, such as `download_payload_rate`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct BytesPerSec(pub u64);
+
+impl From<u64> for BytesPerSec {
+    fn from(n: u64) -> BytesPerSec {
+        BytesPerSec(n)
+    }
+}
+
+impl fmt::Display for BytesPerSec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_binary(f, self.0, "/s")
+    }
+}
+
+impl FromStr for BytesPerSec {
+
+    type Err = ParseError;
+
+    /// Parses rates like "250 KiB/s" or "1.5 MiB", the "/s" is optional.
+    fn from_str(s: &str) -> Result<BytesPerSec, ParseError> {
+        parse_binary(s, "/s").map(BytesPerSec)
+    }
+
+}
+
+/// A share ratio, uploaded bytes over downloaded bytes.
+///
+/// The daemon reports -1 when nothing has been downloaded yet, which displays as "∞" like it
+/// does in the Deluge UIs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Ratio(pub f64);
+
+impl Ratio {
+
+    /// Returns true for the daemon's "nothing downloaded yet" ratio.
+    pub fn is_infinite(&self) -> bool {
+        self.0 < 0.0 || self.0.is_infinite()
+    }
+
+}
+
+impl From<f64> for Ratio {
+    fn from(n: f64) -> Ratio {
+        Ratio(n)
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_infinite() {
+            f.pad("∞")
+        } else {
+            f.pad(&format!("{:.3}", self.0))
+        }
+    }
+}
+
+impl FromStr for Ratio {
+
+    type Err = ParseError;
+
+    /// Parses ratios like "2.750", and "∞" back to -1.
+    fn from_str(s: &str) -> Result<Ratio, ParseError> {
+        match s.trim() {
+            "∞" => Ok(Ratio(-1.0)),
+            s => Ok(Ratio(try!(s.parse()))),
+        }
+    }
+
+}
+
+/// Converts the daemon's `eta` status field, in seconds, to a duration.
+///
+/// The daemon sends 0 when the torrent isn't downloading and -1 when there is no estimate, both
+/// are `None`.
+pub fn eta(secs: i64) -> Option<Duration> {
+    if secs > 0 {
+        Some(Duration::from_secs(secs as u64))
+    } else {
+        None
+    }
+}
+
+/// Displays a duration in its two largest units, like the Deluge UIs show an ETA: "45s",
+/// "3m 20s", "1h 5m", "2d 3h", "1w 2d" or "1y 4w". A year is 52 weeks.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HumanDuration(pub Duration);
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        const MINUTE: u64 = 60;
+        const HOUR: u64 = 60 * MINUTE;
+        const DAY: u64 = 24 * HOUR;
+        const WEEK: u64 = 7 * DAY;
+        const YEAR: u64 = 52 * WEEK;
+
+        let secs = self.0.as_secs();
+        let s = if secs < MINUTE {
+            format!("{}s", secs)
+        } else if secs < HOUR {
+            format!("{}m {}s", secs / MINUTE, secs % MINUTE)
+        } else if secs < DAY {
+            format!("{}h {}m", secs / HOUR, secs % HOUR / MINUTE)
+        } else if secs < WEEK {
+            format!("{}d {}h", secs / DAY, secs % DAY / HOUR)
+        } else if secs < YEAR {
+            format!("{}w {}d", secs / WEEK, secs % WEEK / DAY)
+        } else {
+            format!("{}y {}w", secs / YEAR, secs % YEAR / WEEK)
+        };
+        f.pad(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{eta, Bytes, BytesPerSec, HumanDuration, ParseError, Ratio};
+    use std::time::Duration;
+
+    #[test]
+    fn test_bytes_display() {
+        assert_eq!(Bytes(0).to_string(), "0 B");
+        assert_eq!(Bytes(1023).to_string(), "1023 B");
+        assert_eq!(Bytes(1024).to_string(), "1 KiB");
+        assert_eq!(Bytes(1536).to_string(), "1.5 KiB");
+        assert_eq!(Bytes(1503238553).to_string(), "1.4 GiB");
+        assert_eq!(Bytes(u64::MAX).to_string(), "16 EiB");
+        assert_eq!(format!("{:>8}", Bytes(1024)), "   1 KiB");
+        assert_eq!(BytesPerSec(256000).to_string(), "250 KiB/s");
+        assert_eq!(BytesPerSec(0).to_string(), "0 B/s");
+    }
+
+    #[test]
+    fn test_bytes_parse() {
+        assert_eq!("1.4 GiB".parse(), Ok(Bytes(1503238554)));
+        assert_eq!("512 B".parse(), Ok(Bytes(512)));
+        assert_eq!("2048".parse(), Ok(Bytes(2048)));
+        assert_eq!(" 1kib ".parse(), Ok(Bytes(1024)));
+        assert_eq!("250 KiB/s".parse(), Ok(BytesPerSec(256000)));
+        assert_eq!("1.5 MiB".parse(), Ok(BytesPerSec(1572864)));
+        assert_eq!("1 KB".parse::<Bytes>(), Err(ParseError::UnknownUnit("KB".into())));
+        assert_eq!("-1 B".parse::<Bytes>(), Err(ParseError::OutOfRange));
+        assert_eq!("16 EiB".parse::<Bytes>(), Err(ParseError::OutOfRange));
+        assert!("GiB".parse::<Bytes>().is_err());
+        // displayed values parse back to themselves
+        for &n in &[0, 1023, 1024, 1536, 256000] {
+            assert_eq!(Bytes(n).to_string().parse(), Ok(Bytes(n)));
+        }
+    }
+
+    #[test]
+    fn test_ratio() {
+        assert_eq!(Ratio(2.75).to_string(), "2.750");
+        assert_eq!(Ratio(0.0).to_string(), "0.000");
+        assert_eq!(Ratio(-1.0).to_string(), "∞");
+        assert!(Ratio(-1.0).is_infinite());
+        assert!(!Ratio(0.0).is_infinite());
+        assert_eq!("2.750".parse(), Ok(Ratio(2.75)));
+        assert_eq!("∞".parse(), Ok(Ratio(-1.0)));
+        assert!("abc".parse::<Ratio>().is_err());
+    }
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(3600), Some(Duration::from_secs(3600)));
+        assert_eq!(eta(0), None);
+        assert_eq!(eta(-1), None);
+    }
+
+    #[test]
+    fn test_human_duration() {
+        let human = |secs| HumanDuration(Duration::from_secs(secs)).to_string();
+        assert_eq!(human(0), "0s");
+        assert_eq!(human(45), "45s");
+        assert_eq!(human(200), "3m 20s");
+        assert_eq!(human(3900), "1h 5m");
+        assert_eq!(human(183600), "2d 3h");
+        assert_eq!(human(777600), "1w 2d");
+        assert_eq!(human(33868800), "1y 4w");
+        assert_eq!(format!("{:>6}", HumanDuration(Duration::from_secs(45))), "   45s");
+    }
+}
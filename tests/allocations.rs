@@ -0,0 +1,174 @@
+//! Maximum number of allocations made by representative decode workloads, measured through
+//! `Value::parse` and, with the serde feature, through `decode`.
+//!
+//! Only allocations made by the current thread are counted, so tests can run in parallel.
+
+extern crate deluge;
+
+#[cfg(feature = "serde")]
+use deluge::rencode::decode;
+use deluge::rencode::Value;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn bump() {
+    // try_with fails during thread teardown, those allocations don't matter
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        bump();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        bump();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+// Runs `f` and returns its result along with the number of allocations it made. The result is
+// returned so that freeing it happens outside of the measurement.
+fn count<T, F: FnOnce() -> T>(f: F) -> (T, usize) {
+    let before = ALLOCATIONS.with(|count| count.get());
+    let result = f();
+    let after = ALLOCATIONS.with(|count| count.get());
+    (result, after - before)
+}
+
+fn string(len: usize) -> Value {
+    Value::String("a".repeat(len))
+}
+
+fn list(len: i64) -> Value {
+    Value::List((0..len).map(Value::I64).collect())
+}
+
+// A dict shaped like a small torrent status: 20 keys, a quarter of them with string values.
+fn status() -> Value {
+    let mut map = BTreeMap::new();
+    for i in 0..20 {
+        let value = if i % 4 == 0 {
+            Value::String(format!("value {}", i))
+        } else {
+            Value::I64(i)
+        };
+        map.insert(format!("key_{}", i), value);
+    }
+    Value::Dict(map)
+}
+
+#[test]
+fn test_allocations_scalars() {
+    let (value, allocs) = count(|| Value::parse(&[65u8, 0, 0, 0, 1, 0, 0, 0, 0]).unwrap());
+    assert_eq!(value, Value::I64(1 << 32));
+    assert_eq!(allocs, 0);
+}
+
+#[test]
+fn test_allocations_strings() {
+    // embedded length
+    let data = string(63).to_bytes();
+    let (s, allocs) = count(|| Value::parse(&data).unwrap());
+    assert_eq!(s, string(63));
+    assert!(allocs <= 1, "{} allocations", allocs);
+
+    // length prefix
+    let data = string(1000).to_bytes();
+    let (s, allocs) = count(|| Value::parse(&data).unwrap());
+    assert_eq!(s, string(1000));
+    assert!(allocs <= 1, "{} allocations", allocs);
+}
+
+#[test]
+fn test_allocations_list() {
+    let data = list(63).to_bytes();
+    let (value, allocs) = count(|| Value::parse(&data).unwrap());
+    assert_eq!(value, list(63));
+    assert!(allocs <= 1, "{} allocations", allocs);
+}
+
+#[test]
+fn test_allocations_status() {
+    let data = status().to_bytes();
+    // one per key and per string value, the rest are btree nodes
+    let (value, allocs) = count(|| Value::parse(&data).unwrap());
+    assert_eq!(value, status());
+    assert!(allocs <= 29, "{} allocations", allocs);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_allocations_decode_scalars() {
+    let data = [65u8, 0, 0, 0, 1, 0, 0, 0, 0];
+    let (value, allocs) = count(|| decode::<i64, _>(&data[..]).unwrap());
+    assert_eq!(value, 1 << 32);
+    assert_eq!(allocs, 0);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_allocations_decode_strings() {
+    // embedded length
+    let data = string(63).to_bytes();
+    let (s, allocs) = count(|| decode::<Value, _>(&data[..]).unwrap());
+    assert_eq!(s, string(63));
+    assert!(allocs <= 1, "{} allocations", allocs);
+
+    // length prefix
+    let data = string(1000).to_bytes();
+    let (s, allocs) = count(|| decode::<Value, _>(&data[..]).unwrap());
+    assert_eq!(s, string(1000));
+    assert!(allocs <= 1, "{} allocations", allocs);
+
+    // longer than the 64 KiB preallocation cap, the buffer grows twice while it is read
+    let data = string(200000).to_bytes();
+    let (s, allocs) = count(|| decode::<Value, _>(&data[..]).unwrap());
+    assert_eq!(s, string(200000));
+    assert!(allocs <= 3, "{} allocations", allocs);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_allocations_decode_lists() {
+    // a fixed-length list reports its length through size_hint, the Vec is allocated once
+    let data = list(63).to_bytes();
+    let (value, allocs) = count(|| decode::<Vec<i64>, _>(&data[..]).unwrap());
+    assert_eq!(value, (0..63).collect::<Vec<i64>>());
+    assert!(allocs <= 1, "{} allocations", allocs);
+
+    let (value, allocs) = count(|| decode::<Value, _>(&data[..]).unwrap());
+    assert_eq!(value, list(63));
+    assert!(allocs <= 1, "{} allocations", allocs);
+
+    // a TERM-terminated list has no length, the Vec grows from 4 to 64 elements while it is read
+    let data = list(64).to_bytes();
+    let (value, allocs) = count(|| decode::<Vec<i64>, _>(&data[..]).unwrap());
+    assert_eq!(value, (0..64).collect::<Vec<i64>>());
+    assert!(allocs <= 5, "{} allocations", allocs);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_allocations_decode_status() {
+    let data = status().to_bytes();
+    // the same as Value::parse, keys and strings are read into buffers sized from their length
+    let (value, allocs) = count(|| decode::<Value, _>(&data[..]).unwrap());
+    assert_eq!(value, status());
+    assert!(allocs <= 29, "{} allocations", allocs);
+}
@@ -0,0 +1,443 @@
+// Produced by a port of rencode.dumps, not by the reference package. Regenerate with
+// tests/vectors/generate.py once the rencode package is available, do not edit by hand.
+
+fn vectors() -> Vec<Vector> {
+    vec![
+        Vector {
+            name: "none",
+            bytes: b"E",
+            value: Value::None,
+            roundtrip: true,
+        },
+        Vector {
+            name: "true",
+            bytes: b"C",
+            value: Value::Bool(true),
+            roundtrip: true,
+        },
+        Vector {
+            name: "false",
+            bytes: b"D",
+            value: Value::Bool(false),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_zero",
+            bytes: b"\x00",
+            value: Value::I64(0),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_pos_fixed_max",
+            bytes: b"+",
+            value: Value::I64(43),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_neg_fixed_min",
+            bytes: b"F",
+            value: Value::I64(-1),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_neg_fixed_max",
+            bytes: b"e",
+            value: Value::I64(-32),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_neg_fixed_past",
+            bytes: b">\xdf",
+            value: Value::I64(-33),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i8",
+            bytes: b">d",
+            value: Value::I64(100),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i8_min",
+            bytes: b">\x80",
+            value: Value::I64(-128),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i8_max",
+            bytes: b">\x7f",
+            value: Value::I64(127),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i16_low",
+            bytes: b"?\x00\x80",
+            value: Value::I64(128),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i16_neg",
+            bytes: b"?\xff\x7f",
+            value: Value::I64(-129),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i16_max",
+            bytes: b"?\x7f\xff",
+            value: Value::I64(32767),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i32_low",
+            bytes: b"@\x00\x00\x80\x00",
+            value: Value::I64(32768),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i32_neg",
+            bytes: b"@\xff\xff\x7f\xff",
+            value: Value::I64(-32769),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i32_max",
+            bytes: b"@\x7f\xff\xff\xff",
+            value: Value::I64(2147483647),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i64_low",
+            bytes: b"A\x00\x00\x00\x00\x80\x00\x00\x00",
+            value: Value::I64(2147483648),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i64_neg",
+            bytes: b"A\xff\xff\xff\xff\x7f\xff\xff\xff",
+            value: Value::I64(-2147483649),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i64_max",
+            bytes: b"A\x7f\xff\xff\xff\xff\xff\xff\xff",
+            value: Value::I64(9223372036854775807),
+            roundtrip: true,
+        },
+        Vector {
+            name: "int_i64_min",
+            bytes: b"A\x80\x00\x00\x00\x00\x00\x00\x00",
+            value: Value::I64(i64::MIN),
+            roundtrip: true,
+        },
+        Vector {
+            name: "float32",
+            bytes: b"B?\xc0\x00\x00",
+            value: Value::F64(1.5),
+            roundtrip: false,
+        },
+        Vector {
+            name: "float32_neg",
+            bytes: b"B\xbe\x80\x00\x00",
+            value: Value::F64(-0.25),
+            roundtrip: false,
+        },
+        Vector {
+            name: "float64",
+            bytes: b",?\xf8\x00\x00\x00\x00\x00\x00",
+            value: Value::F64(1.5),
+            roundtrip: true,
+        },
+        Vector {
+            name: "float64_ratio",
+            bytes: b",?\xbf\x9a\xdd79c_",
+            value: Value::F64(0.123456789),
+            roundtrip: true,
+        },
+        Vector {
+            name: "str_empty",
+            bytes: b"\x80",
+            value: s(""),
+            roundtrip: true,
+        },
+        Vector {
+            name: "str_fixed",
+            bytes: b"\x83abc",
+            value: s("abc"),
+            roundtrip: true,
+        },
+        Vector {
+            name: "str_fixed_max",
+            bytes: b"\xbfaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            value: s("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"),
+            roundtrip: true,
+        },
+        Vector {
+            name: "str_prefixed",
+            bytes: b"64:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",
+            value: s("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"),
+            roundtrip: true,
+        },
+        Vector {
+            name: "str_utf8",
+            bytes: b"\x89d\xc3\xa9j\xc3\xa0 vu",
+            value: s("d\u{e9}j\u{e0} vu"),
+            roundtrip: true,
+        },
+        Vector {
+            name: "list_empty",
+            bytes: b"\xc0",
+            value: list(vec![]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "list_fixed",
+            bytes: b"\xc3\x01\x81aE",
+            value: list(vec![
+                Value::I64(1),
+                s("a"),
+                Value::None,
+            ]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "list_fixed_max",
+            bytes: b"\xff\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x1a\x1b\x1c\x1d\x1e\x1f !\"#$%&'()*+>,>->.>/>0>1>2>3>4>5>6>7>8>9>:>;><>=>>",
+            value: list(vec![
+                Value::I64(0),
+                Value::I64(1),
+                Value::I64(2),
+                Value::I64(3),
+                Value::I64(4),
+                Value::I64(5),
+                Value::I64(6),
+                Value::I64(7),
+                Value::I64(8),
+                Value::I64(9),
+                Value::I64(10),
+                Value::I64(11),
+                Value::I64(12),
+                Value::I64(13),
+                Value::I64(14),
+                Value::I64(15),
+                Value::I64(16),
+                Value::I64(17),
+                Value::I64(18),
+                Value::I64(19),
+                Value::I64(20),
+                Value::I64(21),
+                Value::I64(22),
+                Value::I64(23),
+                Value::I64(24),
+                Value::I64(25),
+                Value::I64(26),
+                Value::I64(27),
+                Value::I64(28),
+                Value::I64(29),
+                Value::I64(30),
+                Value::I64(31),
+                Value::I64(32),
+                Value::I64(33),
+                Value::I64(34),
+                Value::I64(35),
+                Value::I64(36),
+                Value::I64(37),
+                Value::I64(38),
+                Value::I64(39),
+                Value::I64(40),
+                Value::I64(41),
+                Value::I64(42),
+                Value::I64(43),
+                Value::I64(44),
+                Value::I64(45),
+                Value::I64(46),
+                Value::I64(47),
+                Value::I64(48),
+                Value::I64(49),
+                Value::I64(50),
+                Value::I64(51),
+                Value::I64(52),
+                Value::I64(53),
+                Value::I64(54),
+                Value::I64(55),
+                Value::I64(56),
+                Value::I64(57),
+                Value::I64(58),
+                Value::I64(59),
+                Value::I64(60),
+                Value::I64(61),
+                Value::I64(62),
+            ]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "list_term",
+            bytes: b";\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x1a\x1b\x1c\x1d\x1e\x1f !\"#$%&'()*+>,>->.>/>0>1>2>3>4>5>6>7>8>9>:>;><>=>>>?\x7f",
+            value: list(vec![
+                Value::I64(0),
+                Value::I64(1),
+                Value::I64(2),
+                Value::I64(3),
+                Value::I64(4),
+                Value::I64(5),
+                Value::I64(6),
+                Value::I64(7),
+                Value::I64(8),
+                Value::I64(9),
+                Value::I64(10),
+                Value::I64(11),
+                Value::I64(12),
+                Value::I64(13),
+                Value::I64(14),
+                Value::I64(15),
+                Value::I64(16),
+                Value::I64(17),
+                Value::I64(18),
+                Value::I64(19),
+                Value::I64(20),
+                Value::I64(21),
+                Value::I64(22),
+                Value::I64(23),
+                Value::I64(24),
+                Value::I64(25),
+                Value::I64(26),
+                Value::I64(27),
+                Value::I64(28),
+                Value::I64(29),
+                Value::I64(30),
+                Value::I64(31),
+                Value::I64(32),
+                Value::I64(33),
+                Value::I64(34),
+                Value::I64(35),
+                Value::I64(36),
+                Value::I64(37),
+                Value::I64(38),
+                Value::I64(39),
+                Value::I64(40),
+                Value::I64(41),
+                Value::I64(42),
+                Value::I64(43),
+                Value::I64(44),
+                Value::I64(45),
+                Value::I64(46),
+                Value::I64(47),
+                Value::I64(48),
+                Value::I64(49),
+                Value::I64(50),
+                Value::I64(51),
+                Value::I64(52),
+                Value::I64(53),
+                Value::I64(54),
+                Value::I64(55),
+                Value::I64(56),
+                Value::I64(57),
+                Value::I64(58),
+                Value::I64(59),
+                Value::I64(60),
+                Value::I64(61),
+                Value::I64(62),
+                Value::I64(63),
+            ]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "dict_empty",
+            bytes: b"f",
+            value: dict(vec![]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "dict_fixed",
+            bytes: b"h\x81a\x01\x81b\x81c",
+            value: dict(vec![
+                ("a", Value::I64(1)),
+                ("b", s("c")),
+            ]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "dict_fixed_max",
+            bytes: b"~\x83k00\x00\x83k01\x01\x83k02\x02\x83k03\x03\x83k04\x04\x83k05\x05\x83k06\x06\x83k07\x07\x83k08\x08\x83k09\x09\x83k10\x0a\x83k11\x0b\x83k12\x0c\x83k13\x0d\x83k14\x0e\x83k15\x0f\x83k16\x10\x83k17\x11\x83k18\x12\x83k19\x13\x83k20\x14\x83k21\x15\x83k22\x16\x83k23\x17",
+            value: dict(vec![
+                ("k00", Value::I64(0)),
+                ("k01", Value::I64(1)),
+                ("k02", Value::I64(2)),
+                ("k03", Value::I64(3)),
+                ("k04", Value::I64(4)),
+                ("k05", Value::I64(5)),
+                ("k06", Value::I64(6)),
+                ("k07", Value::I64(7)),
+                ("k08", Value::I64(8)),
+                ("k09", Value::I64(9)),
+                ("k10", Value::I64(10)),
+                ("k11", Value::I64(11)),
+                ("k12", Value::I64(12)),
+                ("k13", Value::I64(13)),
+                ("k14", Value::I64(14)),
+                ("k15", Value::I64(15)),
+                ("k16", Value::I64(16)),
+                ("k17", Value::I64(17)),
+                ("k18", Value::I64(18)),
+                ("k19", Value::I64(19)),
+                ("k20", Value::I64(20)),
+                ("k21", Value::I64(21)),
+                ("k22", Value::I64(22)),
+                ("k23", Value::I64(23)),
+            ]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "dict_term",
+            bytes: b"<\x83k00\x00\x83k01\x01\x83k02\x02\x83k03\x03\x83k04\x04\x83k05\x05\x83k06\x06\x83k07\x07\x83k08\x08\x83k09\x09\x83k10\x0a\x83k11\x0b\x83k12\x0c\x83k13\x0d\x83k14\x0e\x83k15\x0f\x83k16\x10\x83k17\x11\x83k18\x12\x83k19\x13\x83k20\x14\x83k21\x15\x83k22\x16\x83k23\x17\x83k24\x18\x7f",
+            value: dict(vec![
+                ("k00", Value::I64(0)),
+                ("k01", Value::I64(1)),
+                ("k02", Value::I64(2)),
+                ("k03", Value::I64(3)),
+                ("k04", Value::I64(4)),
+                ("k05", Value::I64(5)),
+                ("k06", Value::I64(6)),
+                ("k07", Value::I64(7)),
+                ("k08", Value::I64(8)),
+                ("k09", Value::I64(9)),
+                ("k10", Value::I64(10)),
+                ("k11", Value::I64(11)),
+                ("k12", Value::I64(12)),
+                ("k13", Value::I64(13)),
+                ("k14", Value::I64(14)),
+                ("k15", Value::I64(15)),
+                ("k16", Value::I64(16)),
+                ("k17", Value::I64(17)),
+                ("k18", Value::I64(18)),
+                ("k19", Value::I64(19)),
+                ("k20", Value::I64(20)),
+                ("k21", Value::I64(21)),
+                ("k22", Value::I64(22)),
+                ("k23", Value::I64(23)),
+                ("k24", Value::I64(24)),
+            ]),
+            roundtrip: true,
+        },
+        Vector {
+            name: "status",
+            bytes: b"n\x85files\xc1i\x85index\x00\x84path\x9eubuntu-15.10-desktop-amd64.iso\x84size@F@\x00\x00\x84name\x9eubuntu-15.10-desktop-amd64.iso\x89num_peers\x00\x86pausedD\x85ratio,@\x06\x00\x00\x00\x00\x00\x00\x85state\x87Seeding\x8atotal_size@F@\x00\x00\x8ctracker_hostE",
+            value: dict(vec![
+                ("files", list(vec![
+                    dict(vec![
+                        ("index", Value::I64(0)),
+                        ("path", s("ubuntu-15.10-desktop-amd64.iso")),
+                        ("size", Value::I64(1178599424)),
+                    ]),
+                ])),
+                ("name", s("ubuntu-15.10-desktop-amd64.iso")),
+                ("num_peers", Value::I64(0)),
+                ("paused", Value::Bool(false)),
+                ("ratio", Value::F64(2.75)),
+                ("state", s("Seeding")),
+                ("total_size", Value::I64(1178599424)),
+                ("tracker_host", Value::None),
+            ]),
+            roundtrip: true,
+        },
+    ]
+}
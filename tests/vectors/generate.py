@@ -0,0 +1,154 @@
+#!/usr/bin/env python3
+"""Regenerate tests/vectors/data.rs from the reference Python rencode.
+
+    pip install rencode
+    python3 tests/vectors/generate.py > tests/vectors/data.rs
+
+`cargo test --features reference-vectors` runs it and checks that its output
+matches the checked-in file, so the vectors can be verified against the
+reference wherever rencode is installed.
+
+Every vector is encoded with `rencode.dumps` and written out next to the
+`Value` it is expected to decode to. Vectors dumped with 32 bit floats can't
+be reproduced by our encoder (it always writes F64), so they are only checked
+in the decode direction.
+"""
+
+import rencode
+
+
+def sorted_dicts(value):
+    # Value::Dict is a BTreeMap, keys must be emitted in sorted order for the
+    # encoder output to match.
+    if isinstance(value, dict):
+        return {k: sorted_dicts(value[k]) for k in sorted(value)}
+    if isinstance(value, list):
+        return [sorted_dicts(v) for v in value]
+    return value
+
+
+VECTORS = [
+    ('none', None, 32),
+    ('true', True, 32),
+    ('false', False, 32),
+    ('int_zero', 0, 32),
+    ('int_pos_fixed_max', 43, 32),
+    ('int_neg_fixed_min', -1, 32),
+    ('int_neg_fixed_max', -32, 32),
+    ('int_neg_fixed_past', -33, 32),
+    ('int_i8', 100, 32),
+    ('int_i8_min', -128, 32),
+    ('int_i8_max', 127, 32),
+    ('int_i16_low', 128, 32),
+    ('int_i16_neg', -129, 32),
+    ('int_i16_max', 32767, 32),
+    ('int_i32_low', 32768, 32),
+    ('int_i32_neg', -32769, 32),
+    ('int_i32_max', 2147483647, 32),
+    ('int_i64_low', 2147483648, 32),
+    ('int_i64_neg', -2147483649, 32),
+    ('int_i64_max', 9223372036854775807, 32),
+    ('int_i64_min', -9223372036854775808, 32),
+    ('float32', 1.5, 32),
+    ('float32_neg', -0.25, 32),
+    ('float64', 1.5, 64),
+    ('float64_ratio', 0.123456789, 64),
+    ('str_empty', '', 32),
+    ('str_fixed', 'abc', 32),
+    ('str_fixed_max', 'a' * 63, 32),
+    ('str_prefixed', 'a' * 64, 32),
+    ('str_utf8', 'déjà vu', 32),
+    ('list_empty', [], 32),
+    ('list_fixed', [1, 'a', None], 32),
+    ('list_fixed_max', list(range(63)), 32),
+    ('list_term', list(range(64)), 32),
+    ('dict_empty', {}, 32),
+    ('dict_fixed', {'a': 1, 'b': 'c'}, 32),
+    ('dict_fixed_max', {'k%02d' % i: i for i in range(24)}, 32),
+    ('dict_term', {'k%02d' % i: i for i in range(25)}, 32),
+    ('status', {
+        'name': 'ubuntu-15.10-desktop-amd64.iso',
+        'state': 'Seeding',
+        'paused': False,
+        'total_size': 1178599424,
+        'num_peers': 0,
+        'ratio': 2.75,
+        'files': [{'index': 0, 'path': 'ubuntu-15.10-desktop-amd64.iso', 'size': 1178599424}],
+        'tracker_host': None,
+    }, 64),
+]
+
+
+def rust_bytes(data):
+    out = []
+    for b in data:
+        c = chr(b)
+        if c == '"' or c == '\\':
+            out.append('\\' + c)
+        elif 0x20 <= b < 0x7f:
+            out.append(c)
+        else:
+            out.append('\\x%02x' % b)
+    return 'b"%s"' % ''.join(out)
+
+
+def rust_str(value):
+    return '"%s"' % ''.join(c if c.isascii() and c not in '"\\' else '\\u{%x}' % ord(c) for c in value)
+
+
+def rust_value(value, indent):
+    pad = ' ' * indent
+    if value is None:
+        return 'Value::None'
+    if value is True or value is False:
+        return 'Value::Bool(%s)' % str(value).lower()
+    if isinstance(value, int):
+        return 'Value::I64(%d)' % value if value != -9223372036854775808 else 'Value::I64(i64::MIN)'
+    if isinstance(value, float):
+        return 'Value::F64(%r)' % value
+    if isinstance(value, str):
+        return 's(%s)' % rust_str(value)
+    if isinstance(value, list):
+        if not value:
+            return 'list(vec![])'
+        items = ''.join('%s    %s,\n' % (pad, rust_value(v, indent + 4)) for v in value)
+        return 'list(vec![\n%s%s])' % (items, pad)
+    if isinstance(value, dict):
+        if not value:
+            return 'dict(vec![])'
+        items = ''.join('%s    (%s, %s),\n' % (pad, rust_str(k), rust_value(v, indent + 4))
+                        for k, v in value.items())
+        return 'dict(vec![\n%s%s])' % (items, pad)
+    raise TypeError(type(value))
+
+
+def main():
+    print('// Generated by tests/vectors/generate.py from the reference rencode, do not edit by hand.')
+    print()
+    print('fn vectors() -> Vec<Vector> {')
+    print('    vec![')
+    for name, value, float_bits in VECTORS:
+        value = sorted_dicts(value)
+        data = rencode.dumps(value, float_bits)
+        print('        Vector {')
+        print('            name: "%s",' % name)
+        print('            bytes: %s,' % rust_bytes(data))
+        print('            value: %s,' % rust_value(value, 12))
+        print('            roundtrip: %s,' % ('true' if float_bits == 64 or not has_float(value) else 'false'))
+        print('        },')
+    print('    ]')
+    print('}')
+
+
+def has_float(value):
+    if isinstance(value, float):
+        return True
+    if isinstance(value, list):
+        return any(has_float(v) for v in value)
+    if isinstance(value, dict):
+        return any(has_float(v) for v in value.values())
+    return False
+
+
+if __name__ == '__main__':
+    main()